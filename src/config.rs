@@ -0,0 +1,218 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use logic::carver::CarverSettings;
+
+const CONFIG_PATH: &'static str = "rusteezee.cfg";
+
+// a line of a `key = value` file that couldn't be read, line 0 stands for the whole file
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl LineError {
+    pub fn new(line: usize, message: String) -> LineError {
+        LineError {
+            line: line,
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}: {}", self.line, self.message)
+    }
+}
+
+// `key = value` split at the first =, None for blank lines and comments starting with #
+pub fn split_line(line: &str) -> Result<Option<(&str, &str)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut parts = line.splitn(2, '=');
+    let key = parts.next().unwrap_or("").trim();
+    match parts.next() {
+        Some(value) if !key.is_empty() => Ok(Some((key, value.trim()))),
+        _ => Err(format!("expected `key = value`, found `{}`", line)),
+    }
+}
+
+// the error names the key and the value
+pub fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid {} `{}`", key, value))
+}
+
+// hands every `key = value` of text to set and collects the lines that failed
+pub fn parse_lines<F>(text: &str, mut set: F) -> Vec<LineError>
+    where F: FnMut(&str, &str) -> Result<(), String>
+{
+    let mut errors = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let result = match split_line(line) {
+            Ok(Some((key, value))) => set(key, value),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            errors.push(LineError::new(n + 1, e));
+        }
+    }
+    errors
+}
+
+// settings from rusteezee.cfg in the working directory,
+// one `key = value` per line, lines starting with # are comments
+#[derive(Clone, Debug)]
+pub struct Config {
+    // ttf file for the text overlay, the embedded font is used if this is unset or fails to load
+    pub font_path: Option<String>,
+    pub font_size: u32,
+    // pixels around the crosshair the picker reads back and filters, 0 for just the crosshair
+    pub pick_radius: u32,
+    // directory the world gets saved in
+    pub world_dir: String,
+    // world generator seed
+    pub seed: u64,
+    pub caves: CarverSettings,
+    // chunks farther away than this from every player get unloaded
+    pub unload_radius: u8,
+    // memory the loaded chunks may use, in MiB
+    pub chunk_budget: usize,
+    // blocks around the spawn only creative players may change, 0 turns the plugin off
+    pub spawn_protection: i32,
+    // crafting recipes, the embedded ones are used if this can't be read
+    pub recipes_path: String,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            font_path: None,
+            font_size: 24,
+            pick_radius: 0,
+            world_dir: "world".to_string(),
+            seed: 0,
+            caves: CarverSettings::new(),
+            unload_radius: 8,
+            chunk_budget: 256,
+            spawn_protection: 0,
+            recipes_path: "assets/data/recipes.txt".to_string(),
+        }
+    }
+
+    // a missing file just means defaults, broken lines are skipped and returned for the caller to report
+    pub fn load() -> (Config, Vec<String>) {
+        let mut text = String::new();
+        let mut problems = Vec::new();
+        if let Ok(mut file) = File::open(&Path::new(CONFIG_PATH)) {
+            if let Err(e) = file.read_to_string(&mut text) {
+                problems.push(format!("Could not read {}: {}", CONFIG_PATH, e));
+            }
+        }
+        let (config, errors) = Config::parse(&text);
+        problems.extend(errors.iter().map(|e| format!("{}:{}", CONFIG_PATH, e)));
+        (config, problems)
+    }
+
+    // the defaults with every line of text that could be read applied
+    pub fn parse(text: &str) -> (Config, Vec<LineError>) {
+        let mut config = Config::new();
+        let errors = parse_lines(text, |key, value| config.set(key, value));
+        (config, errors)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "font_path" => self.font_path = Some(value.to_string()),
+            "font_size" => self.font_size = try!(parse_value(key, value)),
+            "pick_radius" => self.pick_radius = try!(parse_value(key, value)),
+            "world_dir" => self.world_dir = value.to_string(),
+            "seed" => self.seed = try!(parse_value(key, value)),
+            "cave_frequency" => self.caves.cave_frequency = try!(parse_value(key, value)),
+            "cave_threshold" => self.caves.cave_threshold = try!(parse_value(key, value)),
+            "tunnel_chance" => self.caves.worm_chance = try!(parse_value(key, value)),
+            "ravine_chance" => self.caves.ravine_chance = try!(parse_value(key, value)),
+            "tunnel_radius" => self.caves.worm_radius = try!(parse_value(key, value)),
+            "tunnel_length" => self.caves.worm_length = try!(parse_value(key, value)),
+            "cave_min_depth" => self.caves.min_depth = try!(parse_value(key, value)),
+            "cave_lowest" => self.caves.lowest = try!(parse_value(key, value)),
+            "unload_radius" => self.unload_radius = try!(parse_value(key, value)),
+            "chunk_budget" => self.chunk_budget = try!(parse_value(key, value)),
+            "spawn_protection" => self.spawn_protection = try!(parse_value(key, value)),
+            "recipes_path" => self.recipes_path = value.to_string(),
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_line_skips_blank_lines_and_comments() {
+        assert_eq!(split_line(""), Ok(None));
+        assert_eq!(split_line("   "), Ok(None));
+        assert_eq!(split_line("# seed = 3"), Ok(None));
+        assert_eq!(split_line("  # indented"), Ok(None));
+    }
+
+    #[test]
+    fn split_line_trims_and_splits_at_the_first_equals_sign() {
+        assert_eq!(split_line("seed=3"), Ok(Some(("seed", "3"))));
+        assert_eq!(split_line("  world_dir =  my world  "), Ok(Some(("world_dir", "my world"))));
+        assert_eq!(split_line("a = b = c"), Ok(Some(("a", "b = c"))));
+        assert_eq!(split_line("font_path ="), Ok(Some(("font_path", ""))));
+    }
+
+    #[test]
+    fn split_line_needs_a_key_and_an_equals_sign() {
+        assert!(split_line("seed").is_err());
+        assert!(split_line("= 3").is_err());
+    }
+
+    #[test]
+    fn parse_value_names_key_and_value() {
+        assert_eq!(parse_value::<u32>("font_size", "12"), Ok(12));
+        assert_eq!(parse_value::<u32>("font_size", "big"), Err("invalid font_size `big`".to_string()));
+        assert!(parse_value::<u8>("unload_radius", "300").is_err());
+    }
+
+    #[test]
+    fn parse_lines_reports_every_broken_line() {
+        let mut seen = Vec::new();
+        let errors = parse_lines("a = 1\n\nbroken\n# b = 2\nc = 3\nd = 4", |key, value| {
+            seen.push((key.to_string(), value.to_string()));
+            if key == "c" { Err("no c".to_string()) } else { Ok(()) }
+        });
+        assert_eq!(seen, vec![
+            ("a".to_string(), "1".to_string()),
+            ("c".to_string(), "3".to_string()),
+            ("d".to_string(), "4".to_string()),
+        ]);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].line, 3);
+        assert_eq!(errors[1], LineError::new(5, "no c".to_string()));
+        assert_eq!(errors[1].to_string(), "5: no c");
+    }
+
+    #[test]
+    fn config_keeps_the_defaults_of_broken_lines() {
+        let (config, errors) = Config::parse("seed = 42\nfont_size = huge\nfoo = 1\nworld_dir = saves/a\n");
+        assert_eq!(config.seed, 42);
+        assert_eq!(config.font_size, Config::new().font_size);
+        assert_eq!(config.world_dir, "saves/a");
+        assert_eq!(errors, vec![
+            LineError::new(2, "invalid font_size `huge`".to_string()),
+            LineError::new(3, "unknown key `foo`".to_string()),
+        ]);
+    }
+}
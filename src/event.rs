@@ -19,8 +19,14 @@ pub enum Event {
     Sneak {
         toogle: bool,
     },
-    Attack,
+    Attack {
+        toogle: bool,
+    },
     UseItem,
+    Throw,
+    Select {
+        slot: u8,
+    },
     None,
 }
 
@@ -34,7 +40,7 @@ impl From<GlEvent> for Event {
         use types::HDirection::*;
         use types::VDirection::*;
         match ev {
-            E::MouseInput(Pressed, M::Left ) => Attack,
+            E::MouseInput(state  , M::Left ) => Attack { toogle: state == Pressed },
             E::MouseInput(Pressed, M::Right) => UseItem,
             E::KeyboardInput(state, _, Some(key)) => {
                 let t = state == Pressed;
@@ -49,6 +55,17 @@ impl From<GlEvent> for Event {
                     (_      , V::Right)  => Turn { dir: Right, toogle: t },
                     (_      , V::Space)  => Fly  { dir: Up   , toogle: t },
                     (_      , V::LShift) => Fly  { dir: Down , toogle: t },
+                    (Pressed, V::F)      => Throw,
+                    (Pressed, V::Key1)   => Select { slot: 0 },
+                    (Pressed, V::Key2)   => Select { slot: 1 },
+                    (Pressed, V::Key3)   => Select { slot: 2 },
+                    (Pressed, V::Key4)   => Select { slot: 3 },
+                    (Pressed, V::Key5)   => Select { slot: 4 },
+                    (Pressed, V::Key6)   => Select { slot: 5 },
+                    (Pressed, V::Key7)   => Select { slot: 6 },
+                    (Pressed, V::Key8)   => Select { slot: 7 },
+                    (Pressed, V::Key9)   => Select { slot: 8 },
+                    (Pressed, V::Key0)   => Select { slot: 9 },
                     _ => None,
                 }
 
@@ -0,0 +1,112 @@
+use super::block::Block;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Biome {
+    Plains,
+    Desert,
+    Forest,
+    Mountains,
+    Ocean,
+}
+
+impl Biome {
+    // temperature and humidity are in 0..1
+    pub fn from_climate(temperature: f32, humidity: f32) -> Biome {
+        use self::Biome::*;
+        if humidity > 0.7 {
+            Ocean
+        } else if temperature < 0.3 {
+            Mountains
+        } else if temperature > 0.6 && humidity < 0.35 {
+            Desert
+        } else if humidity > 0.45 {
+            Forest
+        } else {
+            Plains
+        }
+    }
+
+    // stable id used when saving, never reorder these
+    pub fn id(self) -> u8 {
+        use self::Biome::*;
+        match self {
+            Plains    => 0,
+            Desert    => 1,
+            Forest    => 2,
+            Mountains => 3,
+            Ocean     => 4,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Biome> {
+        use self::Biome::*;
+        match id {
+            0 => Some(Plains),
+            1 => Some(Desert),
+            2 => Some(Forest),
+            3 => Some(Mountains),
+            4 => Some(Ocean),
+            _ => None,
+        }
+    }
+
+    // topmost block of a column
+    pub fn surface(self) -> Block {
+        use self::Biome::*;
+        match self {
+            Plains    |
+            Forest    => Block::Grass,
+            Desert    |
+            Ocean     => Block::Sand,
+            Mountains => Block::Snow,
+        }
+    }
+
+    // the few blocks below the surface, stone comes after that
+    pub fn filler(self) -> Block {
+        use self::Biome::*;
+        match self {
+            Plains    |
+            Forest    => Block::Dirt,
+            Desert    |
+            Ocean     => Block::Sand,
+            Mountains => Block::Stone,
+        }
+    }
+
+    // average terrain height in blocks
+    pub fn base_height(self) -> f32 {
+        use self::Biome::*;
+        match self {
+            Plains    => 12.0,
+            Desert    => 11.0,
+            Forest    => 14.0,
+            Mountains => 30.0,
+            Ocean     => -6.0,
+        }
+    }
+
+    // how far the terrain deviates from the base height
+    pub fn height_scale(self) -> f32 {
+        use self::Biome::*;
+        match self {
+            Plains    => 3.0,
+            Desert    => 4.0,
+            Forest    => 5.0,
+            Mountains => 24.0,
+            Ocean     => 4.0,
+        }
+    }
+
+    // chance of a decoration like a tree per column
+    pub fn decoration_density(self) -> f32 {
+        use self::Biome::*;
+        match self {
+            Plains    => 0.002,
+            Desert    => 0.001,
+            Forest    => 0.03,
+            Mountains => 0.004,
+            Ocean     => 0.0,
+        }
+    }
+}
@@ -1,33 +1,445 @@
 use cgmath::Point3;
 
-use types::Face;
+use types::{ Aabb, Axis, Face };
+use super::item::{ Item, Tool };
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Block {
     Air,
     Dirt,
+    Leaves,
+    Glass,
+    Water,
+    Stone,
+    Sand,
+    Grass,
+    Snow,
+    Log,
+    Furnace,
+    Slab,
+    Stairs,
+    Fence,
+    TallGrass,
+}
+
+// per block data packed into one byte:
+// 3 bits facing | 2 bits axis | 1 bit open | 2 bits growth stage
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockState(pub u8);
+
+impl BlockState {
+    pub fn new() -> BlockState {
+        BlockState(0)
+    }
+
+    // the side furnaces, doors and the like have their front on
+    pub fn facing(self) -> Face {
+        Face::from((self.0 & 0x7) as u32 % 6)
+    }
+
+    pub fn with_facing(self, facing: Face) -> BlockState {
+        BlockState(self.0 & !0x7 | facing as u8)
+    }
+
+    // the direction logs and pillars run in, upright is 0 so generated
+    // and old blocks without a state stand up
+    pub fn axis(self) -> Axis {
+        match (self.0 >> 3) & 0x3 {
+            0 => Axis::Y,
+            1 => Axis::X,
+            _ => Axis::Z,
+        }
+    }
+
+    pub fn with_axis(self, axis: Axis) -> BlockState {
+        let bits = match axis {
+            Axis::Y => 0,
+            Axis::X => 1,
+            Axis::Z => 2,
+        };
+        BlockState(self.0 & !(0x3 << 3) | bits << 3)
+    }
+
+    pub fn is_open(self) -> bool {
+        self.0 & (1 << 5) != 0
+    }
+
+    pub fn with_open(self, open: bool) -> BlockState {
+        BlockState(self.0 & !(1 << 5) | (open as u8) << 5)
+    }
+
+    // 0..3, for plants
+    pub fn growth(self) -> u8 {
+        self.0 >> 6
+    }
+
+    pub fn with_growth(self, growth: u8) -> BlockState {
+        BlockState(self.0 & !(0x3 << 6) | (growth & 0x3) << 6)
+    }
+}
+
+// the geometry of a block
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Empty,
+    Cube,
+    // the lower half when facing up, the upper half when facing down
+    Slab,
+    // a lower slab with a step on the facing half
+    Stairs,
+    // a thin post in the middle of the block
+    Fence,
+    // two diagonal quads, for plants
+    Cross,
+}
+
+impl Shape {
+    // boxes in block coordinates, these are drawn, picked and outlined
+    pub fn boxes(self, state: BlockState) -> Vec<Aabb> {
+        use self::Shape::*;
+        let aabb = |x0, y0, z0, x1, y1, z1| Aabb::new(Point3::new(x0, y0, z0), Point3::new(x1, y1, z1));
+        match self {
+            Empty  => Vec::new(),
+            Cube   => vec![aabb(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)],
+            Slab if state.facing() == Face::Bottom
+                   => vec![aabb(0.0, 0.5, 0.0, 1.0, 1.0, 1.0)],
+            Slab   => vec![aabb(0.0, 0.0, 0.0, 1.0, 0.5, 1.0)],
+            Stairs => vec![
+                aabb(0.0, 0.0, 0.0, 1.0, 0.5, 1.0),
+                match state.facing() {
+                    Face::East  => aabb(0.5, 0.5, 0.0, 1.0, 1.0, 1.0),
+                    Face::South => aabb(0.0, 0.5, 0.5, 1.0, 1.0, 1.0),
+                    Face::West  => aabb(0.0, 0.5, 0.0, 0.5, 1.0, 1.0),
+                    _           => aabb(0.0, 0.5, 0.0, 1.0, 1.0, 0.5),
+                },
+            ],
+            Fence  => vec![aabb(0.375, 0.0, 0.375, 0.625, 1.0, 0.625)],
+            // only used for picking and the outline, the quads are drawn instead
+            Cross  => vec![aabb(0.125, 0.0, 0.125, 0.875, 0.75, 0.875)],
+        }
+    }
+}
+
+// the render pass a block is drawn in
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Layer {
+    // fully opaque textures
+    Opaque,
+    // alpha tested textures, pixels are either drawn or discarded
+    Cutout,
+    // blended textures, drawn back to front after everything else
+    Translucent,
 }
 
 impl Block {
-    pub fn as_faces(self) -> Vec<(Face, Point3<f32>)> {
-        if self != Block::Air {
-            use types::Face::*;
-            vec![
-                (Top    , Point3::new(0.0, 1.0, 0.0)),
-                (Top    , Point3::new(1.0, 1.0, 1.0)),
-                (Bottom , Point3::new(0.0, 0.0, 1.0)),
-                (Bottom , Point3::new(1.0, 0.0, 0.0)),
-                (North  , Point3::new(1.0, 1.0, 0.0)),
-                (North  , Point3::new(0.0, 0.0, 0.0)),
-                (East   , Point3::new(1.0, 1.0, 1.0)),
-                (East   , Point3::new(1.0, 0.0, 0.0)),
-                (South  , Point3::new(0.0, 1.0, 1.0)),
-                (South  , Point3::new(1.0, 0.0, 1.0)),
-                (West   , Point3::new(0.0, 1.0, 0.0)),
-                (West   , Point3::new(0.0, 0.0, 1.0)),
-            ]
-        } else {
-            Vec::new()
+    // stable id used when saving, never reorder these
+    pub fn id(self) -> u8 {
+        use self::Block::*;
+        match self {
+            Air    => 0,
+            Dirt   => 1,
+            Leaves => 2,
+            Glass  => 3,
+            Water  => 4,
+            Stone  => 5,
+            Sand   => 6,
+            Grass  => 7,
+            Snow   => 8,
+            Log    => 9,
+            Furnace => 10,
+            Slab   => 11,
+            Stairs => 12,
+            Fence  => 13,
+            TallGrass => 14,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Block> {
+        use self::Block::*;
+        match id {
+            0 => Some(Air),
+            1 => Some(Dirt),
+            2 => Some(Leaves),
+            3 => Some(Glass),
+            4 => Some(Water),
+            5 => Some(Stone),
+            6 => Some(Sand),
+            7 => Some(Grass),
+            8 => Some(Snow),
+            9 => Some(Log),
+            10 => Some(Furnace),
+            11 => Some(Slab),
+            12 => Some(Stairs),
+            13 => Some(Fence),
+            14 => Some(TallGrass),
+            _ => None,
+        }
+    }
+
+    pub fn layer(self) -> Layer {
+        use self::Block::*;
+        match self {
+            Air    |
+            Dirt   |
+            Stone  |
+            Sand   |
+            Grass  |
+            Snow   |
+            Log    |
+            Furnace |
+            Slab   |
+            Stairs |
+            Fence  => Layer::Opaque,
+            Leaves |
+            TallGrass => Layer::Cutout,
+            Glass  |
+            Water  => Layer::Translucent,
+        }
+    }
+
+    pub fn shape(self) -> Shape {
+        use self::Block::*;
+        match self {
+            Air    => Shape::Empty,
+            Slab   => Shape::Slab,
+            Stairs => Shape::Stairs,
+            Fence  => Shape::Fence,
+            TallGrass => Shape::Cross,
+            _      => Shape::Cube,
+        }
+    }
+
+    // what is left when the block gets broken, as items and counts
+    pub fn drops(self) -> Vec<(Item, u8)> {
+        use self::Block::*;
+        match self {
+            Air    |
+            Water  |
+            Glass  |
+            Leaves |
+            TallGrass => Vec::new(),
+            Grass  => vec![(Item::Block(Dirt), 1)],
+            _      => vec![(Item::Block(self), 1)],
+        }
+    }
+
+    // seconds to break the block by hand, None if it can't be broken
+    pub fn hardness(self) -> Option<f32> {
+        use self::Block::*;
+        match self {
+            Air    |
+            Water  => None,
+            TallGrass => Some(0.0),
+            Snow   => Some(0.2),
+            Leaves |
+            Glass  => Some(0.3),
+            Dirt   |
+            Sand   => Some(0.5),
+            Grass  => Some(0.6),
+            Log    |
+            Fence  => Some(2.0),
+            Stone  |
+            Slab   |
+            Stairs => Some(3.0),
+            Furnace => Some(3.5),
+        }
+    }
+
+    // the tool that breaks the block faster
+    pub fn tool(self) -> Option<Tool> {
+        use self::Block::*;
+        match self {
+            Stone  |
+            Slab   |
+            Stairs |
+            Furnace => Some(Tool::Pickaxe),
+            Log    |
+            Fence  => Some(Tool::Axe),
+            Dirt   |
+            Grass  |
+            Sand   |
+            Snow   => Some(Tool::Shovel),
+            _      => None,
+        }
+    }
+
+    // seconds to break the block with the held tool, None if it can't be broken
+    pub fn break_time(self, held: Option<Tool>) -> Option<f32> {
+        self.hardness().map(|hardness| match (held, self.tool()) {
+            (Some(held), Some(tool)) if held == tool => hardness / tool.speed(),
+            _ => hardness,
+        })
+    }
+
+    // state of a block placed on the face of the targeted block by a player looking towards facing
+    pub fn placed_state(self, target: Face, facing: Face) -> BlockState {
+        use self::Block::*;
+        match self {
+            Log     => BlockState::new().with_axis(target.axis()),
+            // the front looks at the player
+            Furnace => BlockState::new().with_facing(facing.opposite()),
+            // upside down when placed against the bottom of a block
+            Slab if target == Face::Bottom => BlockState::new().with_facing(Face::Bottom),
+            // the step is on the far side
+            Stairs  => BlockState::new().with_facing(facing),
+            _       => BlockState::new(),
+        }
+    }
+
+    // index into the block texture array, see render::texture
+    pub fn texture(self, face: Face, state: BlockState) -> u8 {
+        use self::Block::*;
+        match self {
+            Air    => 0,
+            Dirt   => 0,
+            Leaves => 1,
+            Glass  => 2,
+            Water  => 3,
+            Stone  => 4,
+            Sand   => 5,
+            Grass  => 6,
+            Snow   => 7,
+            // the cut shows on both ends of the axis
+            Log if face.axis() == state.axis() => 9,
+            Log    => 8,
+            Furnace if face == state.facing() => 10,
+            Furnace => 11,
+            Slab   |
+            Stairs => 4,
+            Fence  => 8,
+            TallGrass => 12,
+        }
+    }
+
+    // quarter turns of the texture on a face, so that the grain of logs follows their axis
+    pub fn texture_rotation(self, face: Face, state: BlockState) -> u8 {
+        match (self, state.axis(), face.axis()) {
+            (Block::Log, Axis::X, Axis::Y) |
+            (Block::Log, Axis::X, Axis::Z) |
+            (Block::Log, Axis::Z, Axis::X) => 1,
+            _ => 0,
+        }
+    }
+
+    // color of the fog when the eye is inside of this block
+    pub fn fog_color(self) -> Option<[f32; 3]> {
+        use self::Block::*;
+        match self {
+            Air    |
+            Glass  |
+            TallGrass => None,
+            Dirt   => Some([0.23, 0.15, 0.08]),
+            Leaves => Some([0.12, 0.35, 0.08]),
+            Water  => Some([0.05, 0.15, 0.45]),
+            Stone  => Some([0.2, 0.2, 0.2]),
+            Sand   => Some([0.55, 0.5, 0.35]),
+            Grass  => Some([0.2, 0.3, 0.1]),
+            Snow   => Some([0.8, 0.82, 0.85]),
+            Log    => Some([0.25, 0.18, 0.1]),
+            Furnace |
+            Slab   |
+            Stairs => Some([0.2, 0.2, 0.2]),
+            Fence  => Some([0.25, 0.18, 0.1]),
+        }
+    }
+
+    // boxes entities bump into, in block coordinates
+    pub fn collision_boxes(self, state: BlockState) -> Vec<Aabb> {
+        use self::Block::*;
+        match self {
+            Water  |
+            TallGrass => Vec::new(),
+            _      => self.shape().boxes(state),
         }
     }
+
+    // faces as pairs of opposite corners with their texture coordinates
+    pub fn as_faces(self, state: BlockState) -> Vec<(Face, Point3<f32>, [f32; 2])> {
+        use types::Face::*;
+        if self.shape() == Shape::Cross {
+            // picking one of them targets the top, so blocks get placed above the plant
+            return vec![
+                (Top, Point3::new(0.0, 1.0, 0.0), [0.0, 1.0]),
+                (Top, Point3::new(1.0, 0.0, 1.0), [1.0, 0.0]),
+                (Top, Point3::new(0.0, 1.0, 1.0), [0.0, 1.0]),
+                (Top, Point3::new(1.0, 0.0, 0.0), [1.0, 0.0]),
+            ];
+        }
+        self.shape().boxes(state).iter().flat_map(box_faces).collect()
+    }
+}
+
+// the six faces of a box as pairs of opposite corners with their texture coordinates
+pub fn box_faces(aabb: &Aabb) -> Vec<(Face, Point3<f32>, [f32; 2])> {
+    use types::Face::*;
+    // corners of the unit cube, scaled to the box
+    const CUBE: [(Face, [f32; 3]); 12] = [
+        (Top    , [0.0, 1.0, 0.0]),
+        (Top    , [1.0, 1.0, 1.0]),
+        (Bottom , [0.0, 0.0, 1.0]),
+        (Bottom , [1.0, 0.0, 0.0]),
+        (North  , [1.0, 1.0, 0.0]),
+        (North  , [0.0, 0.0, 0.0]),
+        (East   , [1.0, 1.0, 1.0]),
+        (East   , [1.0, 0.0, 0.0]),
+        (South  , [0.0, 1.0, 1.0]),
+        (South  , [1.0, 0.0, 1.0]),
+        (West   , [0.0, 1.0, 0.0]),
+        (West   , [0.0, 0.0, 1.0]),
+    ];
+    let (min, max) = (aabb.min, aabb.max);
+    CUBE.iter().map(|&(f, c)| {
+        let p = Point3::new(
+            min.x + (max.x - min.x) * c[0],
+            min.y + (max.y - min.y) * c[1],
+            min.z + (max.z - min.z) * c[2],
+        );
+        (f, p, face_uv(f, p))
+    }).collect()
+}
+
+// texture coordinates of a point on a face, partial faces show the matching part of the texture
+fn face_uv(face: Face, p: Point3<f32>) -> [f32; 2] {
+    use types::Face::*;
+    match face {
+        Top    => [p.x, 1.0 - p.z],
+        Bottom => [p.x, p.z],
+        North  => [1.0 - p.x, p.y],
+        East   => [1.0 - p.z, p.y],
+        South  => [p.x, p.y],
+        West   => [p.z, p.y],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::{ Axis, Face };
+    use super::*;
+
+    #[test]
+    fn blocks_without_a_state_stand_upright() {
+        assert_eq!(BlockState::new().axis(), Axis::Y);
+        assert_eq!(Block::Log.texture(Face::Top, BlockState::new()), 9);
+        assert_eq!(Block::Log.texture(Face::North, BlockState::new()), 8);
+        assert_eq!(Block::Log.texture_rotation(Face::North, BlockState::new()), 0);
+    }
+
+    #[test]
+    fn the_axis_keeps_the_other_bits() {
+        let state = BlockState::new().with_facing(Face::West).with_open(true).with_growth(3);
+        for &axis in [Axis::X, Axis::Y, Axis::Z].iter() {
+            let with_axis = state.with_axis(axis);
+            assert_eq!(with_axis.axis(), axis);
+            assert_eq!(with_axis.facing(), Face::West);
+            assert!(with_axis.is_open());
+            assert_eq!(with_axis.growth(), 3);
+        }
+    }
+
+    #[test]
+    fn logs_run_along_the_face_they_are_placed_on() {
+        assert_eq!(Block::Log.placed_state(Face::Top, Face::North).axis(), Axis::Y);
+        assert_eq!(Block::Log.placed_state(Face::East, Face::North).axis(), Axis::X);
+        assert_eq!(Block::Log.placed_state(Face::South, Face::North).axis(), Axis::Z);
+    }
 }
@@ -0,0 +1,186 @@
+use std::cmp;
+use std::f32::consts::PI;
+
+use cgmath::Point3;
+
+use super::block::Block;
+use super::chunk::Chunk;
+use super::chunks::ChunkPos;
+use super::noise::{ self, Noise };
+
+// worms start in cubic cells of this size, at most one per cell
+const WORM_CELL: i32 = 48;
+// distance a worm moves per step in blocks
+const WORM_STEP: f32 = 1.0;
+// ravines are worms stretched vertically by this factor
+const RAVINE_STRETCH: f32 = 3.0;
+
+#[derive(Clone, Debug)]
+pub struct CarverSettings {
+    // frequency of the 3d noise caves in 1/blocks
+    pub cave_frequency: f32,
+    // noise values above this get carved, higher means fewer and smaller caves
+    pub cave_threshold: f32,
+    // chance that a worm cell contains a tunnel
+    pub worm_chance: f32,
+    // chance that a tunnel is a ravine instead
+    pub ravine_chance: f32,
+    // radius of tunnels in blocks
+    pub worm_radius: f32,
+    // steps per tunnel
+    pub worm_length: i32,
+    // nothing gets carved closer than this to the surface
+    pub min_depth: i32,
+    // nothing gets carved below this height
+    pub lowest: i32,
+}
+
+impl CarverSettings {
+    pub fn new() -> CarverSettings {
+        CarverSettings {
+            cave_frequency: 1.0 / 24.0,
+            cave_threshold: 0.45,
+            worm_chance: 0.5,
+            ravine_chance: 0.1,
+            worm_radius: 2.5,
+            worm_length: 80,
+            min_depth: 4,
+            lowest: -128,
+        }
+    }
+}
+
+// removes blocks below the surface, only depends on the seed and the world position
+// so caves continue seamlessly into every neighbouring chunk, above and below too
+#[derive(Debug)]
+pub struct Carver {
+    seed: u64,
+    caves: Noise,
+    settings: CarverSettings,
+}
+
+impl Carver {
+    pub fn new(seed: u64, settings: CarverSettings) -> Carver {
+        Carver {
+            seed: noise::hash(seed, 0, 4, 0),
+            caves: Noise::new(seed).derive(4),
+            settings: settings,
+        }
+    }
+
+    // heights holds the topmost solid block of every column, indexed by x and z
+    pub fn carve(&self, chunk: &mut Chunk, pos: ChunkPos, heights: &[[i32; 16]; 16]) {
+        self.carve_caves(chunk, pos, heights);
+        self.carve_worms(chunk, pos, heights);
+    }
+
+    fn can_carve(&self, chunk: &Chunk, block: Point3<u8>, wy: i32, height: i32) -> bool {
+        wy >= self.settings.lowest
+            && wy <= height - self.settings.min_depth
+            && chunk[block] != Block::Air
+            && chunk[block] != Block::Water
+    }
+
+    fn carve_caves(&self, chunk: &mut Chunk, pos: ChunkPos, heights: &[[i32; 16]; 16]) {
+        let f = self.settings.cave_frequency;
+        for x in 0..16u8 {
+            for z in 0..16u8 {
+                let height = heights[x as usize][z as usize];
+                for y in 0..16u8 {
+                    let (wx, wy, wz) = (pos.x * 16 + x as i32, pos.y * 16 + y as i32, pos.z * 16 + z as i32);
+                    let block = Point3::new(x, y, z);
+                    if !self.can_carve(chunk, block, wy, height) {
+                        continue;
+                    }
+                    // squashed vertically, caves are wider than they are high
+                    if self.caves.fractal3(wx as f32 * f, wy as f32 * f * 2.0, wz as f32 * f, 3) > self.settings.cave_threshold {
+                        chunk.set(block, Block::Air);
+                    }
+                }
+            }
+        }
+    }
+
+    fn carve_worms(&self, chunk: &mut Chunk, pos: ChunkPos, heights: &[[i32; 16]; 16]) {
+        let s = &self.settings;
+        // farthest a worm can reach from its start
+        let reach = (s.worm_length as f32 * WORM_STEP + s.worm_radius * RAVINE_STRETCH).ceil() as i32;
+        let min = Point3::new(pos.x * 16, pos.y * 16, pos.z * 16);
+        let cell = |v: i32| (v as f32 / WORM_CELL as f32).floor() as i32;
+        for cx in cell(min.x - reach)..cell(min.x + 16 + reach) + 1 {
+            for cy in cell(min.y - reach)..cell(min.y + 16 + reach) + 1 {
+                for cz in cell(min.z - reach)..cell(min.z + 16 + reach) + 1 {
+                    let h = noise::hash(self.seed, cx, cy, cz);
+                    if noise::hash_unit(h, 0, 0, 0) >= s.worm_chance {
+                        continue;
+                    }
+                    self.carve_worm(chunk, min, heights, Point3::new(cx, cy, cz), h);
+                }
+            }
+        }
+    }
+
+    // follows one worm from start to end and carves what lies inside of the chunk
+    fn carve_worm(&self, chunk: &mut Chunk, min: Point3<i32>, heights: &[[i32; 16]; 16], cell: Point3<i32>, h: u64) {
+        let s = &self.settings;
+        let rand = |i: i32, j: i32| noise::hash_unit(h, i, j, 1);
+
+        let ravine = rand(0, 1) < s.ravine_chance;
+        let stretch = if ravine { RAVINE_STRETCH } else { 1.0 };
+        let mut p = [
+            (cell.x * WORM_CELL) as f32 + rand(0, 2) * WORM_CELL as f32,
+            (cell.y * WORM_CELL) as f32 + rand(0, 3) * WORM_CELL as f32,
+            (cell.z * WORM_CELL) as f32 + rand(0, 4) * WORM_CELL as f32,
+        ];
+        let mut yaw = rand(0, 5) * 2.0 * PI;
+        // ravines stay level, tunnels go up and down a little
+        let mut pitch = if ravine { 0.0 } else { (rand(0, 6) - 0.5) * 0.5 };
+
+        for step in 0..s.worm_length {
+            // thin at both ends, widest in the middle
+            let taper = (PI * step as f32 / s.worm_length as f32).sin();
+            let radius = s.worm_radius * (0.5 + 0.5 * taper);
+            self.carve_ellipsoid(chunk, min, heights, p, radius, radius * stretch);
+
+            yaw += (rand(step, 7) - 0.5) * 0.6;
+            if !ravine {
+                pitch = pitch * 0.8 + (rand(step, 8) - 0.5) * 0.3;
+            }
+            p[0] += pitch.cos() * yaw.cos() * WORM_STEP;
+            p[1] += pitch.sin() * WORM_STEP;
+            p[2] += pitch.cos() * yaw.sin() * WORM_STEP;
+        }
+    }
+
+    fn carve_ellipsoid(&self, chunk: &mut Chunk, min: Point3<i32>, heights: &[[i32; 16]; 16], center: [f32; 3], radius: f32, radius_y: f32) {
+        // range of the ellipsoid in chunk coordinates, clamped to the chunk
+        let range = |c: f32, r: f32, min: i32| {
+            let lo = cmp::max((c - r).floor() as i32 - min, 0);
+            let hi = cmp::min((c + r).ceil() as i32 - min, 15);
+            (lo, hi)
+        };
+        let (x0, x1) = range(center[0], radius, min.x);
+        let (y0, y1) = range(center[1], radius_y, min.y);
+        let (z0, z1) = range(center[2], radius, min.z);
+        if x0 > x1 || y0 > y1 || z0 > z1 {
+            return;
+        }
+        for x in x0..x1 + 1 {
+            for z in z0..z1 + 1 {
+                let height = heights[x as usize][z as usize];
+                for y in y0..y1 + 1 {
+                    let dx = (min.x + x) as f32 + 0.5 - center[0];
+                    let dy = (min.y + y) as f32 + 0.5 - center[1];
+                    let dz = (min.z + z) as f32 + 0.5 - center[2];
+                    if (dx * dx + dz * dz) / (radius * radius) + dy * dy / (radius_y * radius_y) > 1.0 {
+                        continue;
+                    }
+                    let block = Point3::new(x as u8, y as u8, z as u8);
+                    if self.can_carve(chunk, block, min.y + y, height) {
+                        chunk.set(block, Block::Air);
+                    }
+                }
+            }
+        }
+    }
+}
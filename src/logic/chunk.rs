@@ -1,16 +1,35 @@
-use std::ops::{ Index, IndexMut };
+use std::ops::Index;
+use std::sync::atomic::{ AtomicUsize, Ordering, ATOMIC_USIZE_INIT };
 
 use cgmath::Point3;
 
-use super::block::Block;
+use super::biome::Biome;
+use super::block::{ Block, BlockState, Layer };
 use ::render::FaceVertex;
 
 pub type BlockPos = Point3<u8>;
 
+const BLOCK_BYTES: usize = 16 * 16 * 16;
+const BIOME_BYTES: usize = 16 * 16;
+const STATE_BYTES: usize = 16 * 16 * 16;
+
+// shared by all chunks, so a chunk that is unloaded and loaded again
+// never gets a version a copy of the old one already had
+static NEXT_VERSION: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed) as u64 + 1
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Chunk {
     blocks: [[[Block; 16]; 16]; 16],
-    dirty: bool,
+    states: [[[BlockState; 16]; 16]; 16],
+    // biome of every block column, indexed by x and z
+    biomes: [[Biome; 16]; 16],
+    // goes up with every change of a block or state and is new for every chunk created or loaded,
+    // so copies can tell they are outdated
+    version: u64,
 }
 
 impl Chunk {
@@ -21,28 +40,116 @@ impl Chunk {
     pub fn new_with(block: Block) -> Chunk {
         Chunk {
             blocks: [[[block; 16]; 16]; 16],
-            dirty: false,
+            states: [[[BlockState::new(); 16]; 16]; 16],
+            biomes: [[Biome::Plains; 16]; 16],
+            version: next_version(),
+        }
+    }
+
+    pub fn state(&self, pos: BlockPos) -> BlockState {
+        self.states[pos.x as usize][pos.y as usize][pos.z as usize]
+    }
+
+    pub fn set_state(&mut self, pos: BlockPos, state: BlockState) {
+        let old = &mut self.states[pos.x as usize][pos.y as usize][pos.z as usize];
+        if *old != state {
+            *old = state;
+            self.version = next_version();
+        }
+    }
+
+    pub fn set(&mut self, pos: BlockPos, block: Block) {
+        let old = &mut self.blocks[pos.x as usize][pos.y as usize][pos.z as usize];
+        if *old != block {
+            *old = block;
+            self.version = next_version();
         }
     }
 
-    pub fn clear_dirty(&mut self) {
-        self.dirty = false;
+    pub fn biome(&self, x: u8, z: u8) -> Biome {
+        self.biomes[x as usize][z as usize]
+    }
+
+    pub fn set_biome(&mut self, x: u8, z: u8, biome: Biome) {
+        self.biomes[x as usize][z as usize] = biome;
     }
 
-    pub fn is_dirty(&self) -> bool {
-        self.dirty
+    pub fn version(&self) -> u64 {
+        self.version
     }
 
-    pub fn as_faces(&self) -> Vec<FaceVertex> {
+    // one block id per block, one biome id per column and one state per block,
+    // all x major like the arrays
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BLOCK_BYTES + BIOME_BYTES + STATE_BYTES);
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    bytes.push(self.blocks[x][y][z].id());
+                }
+            }
+        }
+        for x in 0..16 {
+            for z in 0..16 {
+                bytes.push(self.biomes[x][z].id());
+            }
+        }
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    bytes.push(self.states[x][y][z].0);
+                }
+            }
+        }
+        bytes
+    }
+
+    // None if the length is wrong or an id is unknown,
+    // chunks saved before biomes existed are all plains, before states all default
+    pub fn from_bytes(bytes: &[u8]) -> Option<Chunk> {
+        let biome_end = ::std::cmp::min(bytes.len(), BLOCK_BYTES + BIOME_BYTES);
+        if bytes.len() != BLOCK_BYTES
+        && bytes.len() != BLOCK_BYTES + BIOME_BYTES
+        && bytes.len() != BLOCK_BYTES + BIOME_BYTES + STATE_BYTES {
+            return None;
+        }
+        let mut chunk = Chunk::new();
+        for (i, &id) in bytes[..BLOCK_BYTES].iter().enumerate() {
+            chunk.blocks[i / 256][i / 16 % 16][i % 16] = match Block::from_id(id) {
+                Some(block) => block,
+                None => return None,
+            };
+        }
+        for (i, &id) in bytes[BLOCK_BYTES..biome_end].iter().enumerate() {
+            chunk.biomes[i / 16][i % 16] = match Biome::from_id(id) {
+                Some(biome) => biome,
+                None => return None,
+            };
+        }
+        for (i, &state) in bytes[biome_end..].iter().enumerate() {
+            chunk.states[i / 256][i / 16 % 16][i % 16] = BlockState(state);
+        }
+        Some(chunk)
+    }
+
+    pub fn as_faces(&self, layer: Layer) -> Vec<FaceVertex> {
         let mut faces = Vec::new();
         for x in 0..16 {
             for y in 0..16 {
                 for z in 0..16 {
                     let pos = Point3::new(x, y, z);
-                    faces.extend(self[pos].as_faces().iter().map(|&(f, c)|
+                    let block = self[pos];
+                    if block.layer() != layer {
+                        continue;
+                    }
+                    let state = self.state(pos);
+                    faces.extend(block.as_faces(state).iter().map(|&(f, c, uv)|
                         FaceVertex {
                             corner: c.into(),
+                            uv: uv,
                             face: f as u8,
+                            tex_id: block.texture(f, state),
+                            tex_rot: block.texture_rotation(f, state),
                             pos: pos.into()
                         }
                     ));
@@ -60,9 +167,41 @@ impl Index<BlockPos> for Chunk {
     }
 }
 
-impl IndexMut<BlockPos> for Chunk {
-    fn index_mut(&mut self, index: BlockPos) -> &mut Block {
-        self.dirty = true;
-        &mut self.blocks[index.x as usize][index.y as usize][index.z as usize]
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use logic::block::{ Block, BlockState };
+    use super::*;
+
+    #[test]
+    fn setting_the_same_block_keeps_the_version() {
+        let mut chunk = Chunk::new();
+        let pos = Point3::new(1, 2, 3);
+        let version = chunk.version();
+        chunk.set(pos, Block::Air);
+        chunk.set_state(pos, BlockState::new());
+        assert_eq!(chunk.version(), version);
+    }
+
+    #[test]
+    fn changes_bump_the_version() {
+        let mut chunk = Chunk::new();
+        let pos = Point3::new(1, 2, 3);
+        let version = chunk.version();
+        chunk.set(pos, Block::Stone);
+        let after_block = chunk.version();
+        assert!(after_block > version);
+        chunk.set_state(pos, BlockState(1));
+        assert!(chunk.version() > after_block);
+    }
+
+    #[test]
+    fn new_chunks_get_newer_versions() {
+        let mut old = Chunk::new();
+        old.set(Point3::new(0, 0, 0), Block::Stone);
+        let loaded = Chunk::from_bytes(&old.to_bytes()).unwrap();
+        assert!(loaded.version() > old.version());
+        assert!(Chunk::new().version() > loaded.version());
     }
 }
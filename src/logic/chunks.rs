@@ -1,25 +1,112 @@
 use std::ops::{ Index, IndexMut };
 use std::collections::HashMap;
+use std::mem;
 
 use cgmath::{ Point, Point3 };
 
 use super::chunk::Chunk;
+use super::entity::{ Entities, Entity };
+use super::events::GameEvent;
+use super::generator::Generator;
+use super::storage::Storage;
 
 
 pub type ChunkPos = Point3<i32>;
 
+#[derive(Debug)]
+struct Entry {
+    chunk: Chunk,
+    // Chunk::version when it was loaded or last saved, 0 for chunks that have to be saved anyway
+    saved_version: u64,
+    // value of the clock at the last update that needed this chunk
+    last_used: u64,
+    // loaded or spawned with the chunk, but not handed out by take_entities yet
+    entities: Vec<Entity>,
+}
+
+impl Entry {
+    // changed since it was loaded or saved, a mutable borrow alone doesn't count
+    fn modified(&self) -> bool {
+        self.chunk.version() != self.saved_version
+    }
+}
+
+// how many chunks went where, since the world was opened
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChunkStats {
+    pub loaded: usize,
+    pub generated: usize,
+    pub saved: usize,
+    pub unloaded: usize,
+    // unloaded because of the memory budget rather than the distance
+    pub evicted: usize,
+}
+
 #[derive(Debug)]
 pub struct Chunks {
-    chunks: HashMap<ChunkPos, Chunk>,
+    chunks: HashMap<ChunkPos, Entry>,
     empty: Chunk,
+    storage: Storage,
+    generator: Generator,
+    // chunks farther away than this from every player get unloaded
+    unload_radius: u8,
+    // bytes the loaded chunks may use, least recently used chunks get evicted above that
+    budget: usize,
+    clock: u64,
+    // chunks and their entities loaded and unloaded since the last call to take_events, in order
+    events: Vec<GameEvent>,
+    // chunks that couldn't be loaded or saved, since the last call to take_errors
+    errors: Vec<String>,
+    stats: ChunkStats,
 }
 
 impl Chunks {
-    pub fn new() -> Chunks {
+    pub fn new(storage: Storage, generator: Generator, unload_radius: u8, budget: usize) -> Chunks {
         Chunks {
             chunks: HashMap::new(),
             empty: Chunk::new(),
+            storage: storage,
+            generator: generator,
+            unload_radius: unload_radius,
+            budget: budget,
+            clock: 0,
+            events: Vec::new(),
+            errors: Vec::new(),
+            stats: ChunkStats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_loaded(&self, pos: ChunkPos) -> bool {
+        self.chunks.contains_key(&pos)
+    }
+
+    pub fn memory(&self) -> usize {
+        self.chunks.len() * mem::size_of::<Entry>()
+    }
+
+    pub fn get_stats(&self) -> ChunkStats {
+        self.stats
+    }
+
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    pub fn take_errors(&mut self) -> Vec<String> {
+        mem::replace(&mut self.errors, Vec::new())
+    }
+
+    // entities of the chunks loaded since the last call
+    pub fn take_entities(&mut self) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        for entry in self.chunks.values_mut() {
+            entities.extend(entry.entities.drain(..));
         }
+        entities
     }
 
     pub fn around(dist: u8, center: ChunkPos) -> Vec<ChunkPos> {
@@ -35,17 +122,206 @@ impl Chunks {
         }
         res
     }
+
+    // loads everything within radius of a player, then unloads what is too far away
+    // or over the memory budget, modified chunks get saved before they are dropped,
+    // the entities in unloaded chunks are taken out of entities and saved too
+    pub fn update(&mut self, players: &[ChunkPos], radius: u8, entities: &mut Entities) {
+        self.clock += 1;
+        for &player in players {
+            for pos in Chunks::around(radius, player) {
+                self.load(pos);
+            }
+        }
+
+        let unload_radius = ::std::cmp::max(radius, self.unload_radius) as i32;
+        let far: Vec<ChunkPos> = self.chunks.keys().cloned().filter(|pos| {
+            players.iter().all(|player| {
+                let d = *pos - *player;
+                d.x.abs() >= unload_radius || d.y.abs() >= unload_radius || d.z.abs() >= unload_radius
+            })
+        }).collect();
+        for pos in far {
+            self.unload(pos, entities);
+        }
+
+        if self.memory() > self.budget {
+            // chunks needed by this update are never evicted
+            let clock = self.clock;
+            let mut lru: Vec<(u64, ChunkPos)> = self.chunks.iter()
+                .filter(|&(_, entry)| entry.last_used < clock)
+                .map(|(pos, entry)| (entry.last_used, *pos))
+                .collect();
+            lru.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, pos) in lru {
+                if self.memory() <= self.budget {
+                    break;
+                }
+                if self.unload(pos, entities) {
+                    self.stats.evicted += 1;
+                }
+            }
+        }
+    }
+
+    // saves every modified chunk and all entities, used when the world gets closed
+    pub fn save_all(&mut self, entities: &Entities) {
+        let storage = &self.storage;
+        let stats = &mut self.stats;
+        let errors = &mut self.errors;
+        for (pos, entry) in self.chunks.iter_mut() {
+            let mut in_chunk = entities.in_chunk(*pos);
+            in_chunk.extend(entry.entities.iter().cloned());
+            if let Err(e) = storage.save_entities(*pos, &in_chunk) {
+                errors.push(format!("Could not save the entities of chunk {:?}: {}", pos, e));
+            }
+            if !entry.modified() {
+                continue;
+            }
+            match storage.save(*pos, &entry.chunk) {
+                Ok(()) => {
+                    entry.saved_version = entry.chunk.version();
+                    stats.saved += 1;
+                },
+                Err(e) => errors.push(format!("Could not save chunk {:?}: {}", pos, e)),
+            }
+        }
+    }
+
+    fn load(&mut self, pos: ChunkPos) -> &mut Entry {
+        let clock = self.clock;
+        if !self.chunks.contains_key(&pos) {
+            let mut fresh = false;
+            let chunk = match self.storage.load(pos) {
+                Ok(Some(chunk)) => {
+                    self.stats.loaded += 1;
+                    chunk
+                },
+                Ok(None) => {
+                    self.stats.generated += 1;
+                    fresh = true;
+                    self.generator.generate(pos)
+                },
+                Err(e) => {
+                    // the file stays untouched as long as the chunk isn't modified
+                    self.errors.push(format!("Could not load chunk {:?}, generating it instead: {}", pos, e));
+                    self.stats.generated += 1;
+                    self.generator.generate(pos)
+                },
+            };
+            let mut entities = match self.storage.load_entities(pos) {
+                Ok(entities) => entities,
+                Err(e) => {
+                    self.errors.push(format!("Could not load the entities of chunk {:?}: {}", pos, e));
+                    Vec::new()
+                },
+            };
+            if fresh {
+                entities.extend(self.generator.spawn(pos, &chunk));
+            }
+            // a chunk that spawned something gets saved, so it doesn't spawn again next time
+            let saved_version = if fresh && !entities.is_empty() { 0 } else { chunk.version() };
+            self.chunks.insert(pos, Entry { chunk: chunk, saved_version: saved_version, last_used: clock, entities: entities });
+            self.events.push(GameEvent::ChunkLoaded(pos));
+        }
+        let entry = self.chunks.get_mut(&pos).unwrap();
+        entry.last_used = clock;
+        entry
+    }
+
+    // false if the chunk couldn't be saved and has to stay loaded
+    fn unload(&mut self, pos: ChunkPos, entities: &mut Entities) -> bool {
+        let modified = match self.chunks.get(&pos) {
+            Some(entry) => entry.modified(),
+            None => return false,
+        };
+        if modified {
+            if let Err(e) = self.storage.save(pos, &self.chunks[&pos].chunk) {
+                self.errors.push(format!("Could not save chunk {:?}, keeping it loaded: {}", pos, e));
+                return false;
+            }
+            self.stats.saved += 1;
+        }
+        // the entities stay in the world until they are saved
+        let mut in_chunk = entities.in_chunk(pos);
+        in_chunk.extend(self.chunks[&pos].entities.iter().cloned());
+        if let Err(e) = self.storage.save_entities(pos, &in_chunk) {
+            self.errors.push(format!("Could not save the entities of chunk {:?}, keeping it loaded: {}", pos, e));
+            return false;
+        }
+        for id in entities.take_chunk(pos) {
+            self.events.push(GameEvent::EntityUnloaded(id));
+        }
+        self.chunks.remove(&pos);
+        self.events.push(GameEvent::ChunkUnloaded(pos));
+        self.stats.unloaded += 1;
+        true
+    }
 }
 
 impl Index<ChunkPos> for Chunks {
     type Output = Chunk;
+    // chunks that aren't loaded read as air
     fn index(&self, index: ChunkPos) -> &Chunk {
-        self.chunks.get(&index).unwrap_or(&self.empty)
+        self.chunks.get(&index).map(|entry| &entry.chunk).unwrap_or(&self.empty)
     }
 }
 
 impl IndexMut<ChunkPos> for Chunks {
+    // loads or generates the chunk if needed, it gets saved when it's unloaded again
+    // if its version changed meanwhile
     fn index_mut(&mut self, index: ChunkPos) -> &mut Chunk {
-        self.chunks.entry(index).or_insert_with(Chunk::new)
+        &mut self.load(index).chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use logic::block::Block;
+    use logic::carver::CarverSettings;
+    use logic::entity::Entities;
+    use logic::generator::Generator;
+    use logic::storage::Storage;
+    use logic::testing::TempDir;
+    use super::*;
+
+    fn chunks(dir: &TempDir) -> Chunks {
+        let mut generator = Generator::new(1, CarverSettings::new());
+        generator.set_mob_spawning(false);
+        Chunks::new(Storage::new(dir.path()), generator, 1, 1024 * 1024)
+    }
+
+    #[test]
+    fn borrowing_without_a_change_doesnt_save() {
+        let dir = TempDir::new("chunks-borrow");
+        let mut chunks = chunks(&dir);
+        let mut entities = Entities::new();
+        let (here, far) = (Point3::new(0, 20, 0), Point3::new(10, 20, 0));
+        chunks.update(&[here], 1, &mut entities);
+        chunks[here].set(Point3::new(1, 2, 3), Block::Air);
+        chunks.update(&[far], 1, &mut entities);
+        assert!(!chunks.is_loaded(here));
+        assert_eq!(chunks.get_stats().saved, 0);
+    }
+
+    #[test]
+    fn changed_chunks_get_saved() {
+        let dir = TempDir::new("chunks-change");
+        let mut chunks = chunks(&dir);
+        let mut entities = Entities::new();
+        let (here, far) = (Point3::new(0, 20, 0), Point3::new(10, 20, 0));
+        chunks.update(&[here], 1, &mut entities);
+        chunks[here].set(Point3::new(1, 2, 3), Block::Stone);
+        chunks.save_all(&entities);
+        assert_eq!(chunks.get_stats().saved, 1);
+        // saved already, unloading doesn't write it again
+        chunks.update(&[far], 1, &mut entities);
+        assert_eq!(chunks.get_stats().saved, 1);
+
+        chunks.update(&[here], 1, &mut entities);
+        assert_eq!(chunks[here][Point3::new(1, 2, 3)], Block::Stone);
+        assert_eq!(chunks.get_stats().loaded, 1);
     }
 }
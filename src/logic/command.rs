@@ -0,0 +1,262 @@
+use cgmath::Point3;
+
+use super::block::Block;
+use super::game::{ self, GameState };
+use super::item::{ self, Item };
+use super::player::GameMode;
+
+// the kinds of arguments commands take, used for parsing and tab completion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arg {
+    // a world coordinate, `~` and `~5` are relative to the player
+    Coord,
+    Number,
+    Block,
+    GameMode,
+    // the name of a command
+    Command,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Coord { relative: bool, offset: f32 },
+    Number(i64),
+    Block(Block),
+    GameMode(GameMode),
+    Command(&'static str),
+}
+
+impl Value {
+    // the coordinate along an axis, relative ones start at the player's feet
+    pub fn coord(self, axis: usize, game: &GameState) -> f32 {
+        match self {
+            Value::Coord { relative: true, offset } => game.player().pos[axis] + offset,
+            Value::Coord { relative: false, offset } => offset,
+            _ => 0.0,
+        }
+    }
+
+    pub fn number(self) -> i64 {
+        match self {
+            Value::Number(n) => n,
+            _ => 0,
+        }
+    }
+}
+
+impl Arg {
+    fn parse(self, word: &str, commands: &Commands) -> Result<Value, String> {
+        match self {
+            Arg::Coord    => {
+                let (relative, rest) = if word.starts_with('~') { (true, &word[1..]) } else { (false, word) };
+                if relative && rest.is_empty() {
+                    return Ok(Value::Coord { relative: true, offset: 0.0 });
+                }
+                rest.parse()
+                    .map(|offset| Value::Coord { relative: relative, offset: offset })
+                    .map_err(|_| format!("invalid coordinate `{}`", word))
+            },
+            Arg::Number   => word.parse().map(Value::Number).map_err(|_| format!("invalid number `{}`", word)),
+            Arg::Block    => match Item::from_name(&word.to_lowercase()) {
+                Some(Item::Block(block)) => Ok(Value::Block(block)),
+                _ => Err(format!("unknown block `{}`", word)),
+            },
+            Arg::GameMode => GameMode::from_name(word)
+                .map(Value::GameMode)
+                .ok_or(format!("unknown game mode `{}`", word)),
+            Arg::Command  => commands.get(word)
+                .map(|command| Value::Command(command.name))
+                .ok_or(format!("unknown command `{}`", word)),
+        }
+    }
+
+    // every value the argument can take, empty when there are too many
+    fn candidates(self, commands: &Commands) -> Vec<String> {
+        match self {
+            Arg::Coord | Arg::Number => Vec::new(),
+            Arg::Block    => (0..item::FIRST_TOOL_ID).filter_map(Block::from_id)
+                .map(|block| Item::Block(block).to_string().to_lowercase())
+                .collect(),
+            Arg::GameMode => GameMode::values().iter().map(|mode| mode.to_string()).collect(),
+            Arg::Command  => commands.names().iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+// gets the registry it was run from, the game and the parsed arguments,
+// returns the message to show
+pub type Run = fn(&Commands, &mut GameState, &[Value]) -> Result<String, String>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub args: &'static [Arg],
+    // the first this many arguments are required
+    pub required: usize,
+    pub usage: &'static str,
+    pub run: Run,
+}
+
+// all the commands that can be typed into the console, they work without a window too
+pub struct Commands {
+    commands: Vec<Command>,
+}
+
+impl Commands {
+    pub fn new() -> Commands {
+        Commands {
+            commands: Vec::new(),
+        }
+    }
+
+    // the builtin commands
+    pub fn defaults() -> Commands {
+        use self::Arg as A;
+        let mut commands = Commands::new();
+        commands.register(Command { name: "help",     args: &[A::Command],                             required: 0, usage: "/help [command]",               run: help });
+        commands.register(Command { name: "tp",       args: &[A::Coord, A::Coord, A::Coord],           required: 3, usage: "/tp <x> <y> <z>",               run: tp });
+        commands.register(Command { name: "setblock", args: &[A::Coord, A::Coord, A::Coord, A::Block], required: 4, usage: "/setblock <x> <y> <z> <block>", run: setblock });
+        commands.register(Command { name: "time",     args: &[A::Number],                              required: 0, usage: "/time [ticks]",                 run: time });
+        commands.register(Command { name: "gamemode", args: &[A::GameMode],                            required: 1, usage: "/gamemode <mode>",              run: gamemode });
+        commands.register(Command { name: "viewdist", args: &[A::Number],                              required: 1, usage: "/viewdist <chunks>",            run: viewdist });
+        commands
+    }
+
+    // replaces a command with the same name
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.commands.iter().map(|command| command.name).collect()
+    }
+
+    // runs a line like `/tp 0 70 0`, the slash is optional,
+    // the result is the message to show either way
+    pub fn execute(&self, game: &mut GameState, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let line = if line.starts_with('/') { &line[1..] } else { line };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let name = match words.first() {
+            Some(name) => *name,
+            None => return Err("empty command".to_string()),
+        };
+        let args = &words[1..];
+        let command = match self.get(name) {
+            Some(command) => command,
+            None => return match game.plugin_command(name, args) {
+                Some(result) => result,
+                None => Err(format!("unknown command `{}`, try /help", name)),
+            },
+        };
+        if args.len() < command.required || args.len() > command.args.len() {
+            return Err(format!("usage: {}", command.usage));
+        }
+        let values = try!(
+            command.args.iter().zip(args)
+                .map(|(arg, word)| arg.parse(word, self))
+                .collect::<Result<Vec<_>, String>>()
+        );
+        (command.run)(self, game, &values)
+    }
+
+    // the lines the last word can be completed to, sorted, plugin commands included
+    pub fn complete(&self, game: &GameState, line: &str) -> Vec<String> {
+        let slash = line.starts_with('/');
+        let body = if slash { &line[1..] } else { line };
+        let mut words: Vec<&str> = body.split(' ').collect();
+        let last = words.pop().unwrap_or("");
+        let candidates = if words.is_empty() {
+            let mut names = Arg::Command.candidates(self);
+            names.extend(game.plugin_commands().iter().map(|name| name.to_string()));
+            names
+        } else {
+            match self.get(words[0]).and_then(|command| command.args.get(words.len() - 1)) {
+                Some(arg) => arg.candidates(self),
+                None => Vec::new(),
+            }
+        };
+        let mut prefix = if slash { "/".to_string() } else { String::new() };
+        for word in &words {
+            prefix.push_str(word);
+            prefix.push(' ');
+        }
+        let mut lines: Vec<String> = candidates.into_iter()
+            .filter(|candidate| candidate.starts_with(last))
+            .map(|candidate| format!("{}{}", prefix, candidate))
+            .collect();
+        lines.sort();
+        lines
+    }
+}
+
+fn help(commands: &Commands, game: &mut GameState, args: &[Value]) -> Result<String, String> {
+    match args.first() {
+        Some(&Value::Command(name)) => commands.get(name)
+            .map(|command| format!("usage: {}", command.usage))
+            .ok_or(format!("unknown command `{}`", name)),
+        _ => {
+            let mut names = commands.names();
+            names.extend(game.plugin_commands());
+            Ok(format!("commands: {}", names.join(", ")))
+        },
+    }
+}
+
+fn tp(_: &Commands, game: &mut GameState, args: &[Value]) -> Result<String, String> {
+    let pos = Point3::new(args[0].coord(0, game), args[1].coord(1, game), args[2].coord(2, game));
+    game.set_player_pos(pos);
+    Ok(format!("teleported to {:.1}, {:.1}, {:.1}", pos.x, pos.y, pos.z))
+}
+
+fn setblock(_: &Commands, game: &mut GameState, args: &[Value]) -> Result<String, String> {
+    let pos = Point3::new(
+        args[0].coord(0, game).floor() as i32,
+        args[1].coord(1, game).floor() as i32,
+        args[2].coord(2, game).floor() as i32,
+    );
+    let block = match args[3] {
+        Value::Block(block) => block,
+        _ => return Err("expected a block".to_string()),
+    };
+    if !game.get_game_mode().can_interact() {
+        return Err(format!("{} players can't change blocks", game.get_game_mode()));
+    }
+    if !game.set_block(pos, block) {
+        return Err(format!("a plugin refused to set {}, {}, {}", pos.x, pos.y, pos.z));
+    }
+    Ok(format!("set {}, {}, {} to {:?}", pos.x, pos.y, pos.z, block))
+}
+
+fn time(_: &Commands, game: &mut GameState, args: &[Value]) -> Result<String, String> {
+    if let Some(ticks) = args.first() {
+        if ticks.number() < 0 {
+            return Err("the time can't be negative".to_string());
+        }
+        game.set_ticks(ticks.number() as u64);
+    }
+    Ok(format!("time: {} ticks", game.get_ticks()))
+}
+
+fn gamemode(_: &Commands, game: &mut GameState, args: &[Value]) -> Result<String, String> {
+    match args[0] {
+        Value::GameMode(mode) => {
+            game.set_game_mode(mode);
+            Ok(format!("game mode: {}", mode))
+        },
+        _ => Err("expected a game mode".to_string()),
+    }
+}
+
+fn viewdist(_: &Commands, game: &mut GameState, args: &[Value]) -> Result<String, String> {
+    let n = args[0].number();
+    if n < game::MIN_VIEW_DIST as i64 || n > game::MAX_VIEW_DIST as i64 {
+        return Err(format!("the view distance has to be between {} and {} chunks", game::MIN_VIEW_DIST, game::MAX_VIEW_DIST));
+    }
+    game.set_view_dist(n as u8);
+    Ok(format!("view distance: {} chunks", game.get_view_dist()))
+}
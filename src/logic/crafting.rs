@@ -0,0 +1,459 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use config::LineError;
+use super::item::Item;
+
+// used whenever the recipe file can't be read, see the file for the format
+pub const DEFAULT_RECIPES: &'static str = include_str!("../../assets/data/recipes.txt");
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    // cells row by row, None for empty cells
+    Shaped {
+        width: usize,
+        height: usize,
+        cells: Vec<Option<Item>>,
+    },
+    // every ingredient once, in any order and place
+    Shapeless(Vec<Item>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipe {
+    pub pattern: Pattern,
+    pub output: Item,
+    pub count: u8,
+}
+
+// the items put into the crafting grid, one per cell
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<Item>>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Grid {
+        Grid {
+            width: width,
+            height: height,
+            cells: vec![None; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Item> {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, item: Option<Item>) {
+        self.cells[y * self.width + x] = item;
+    }
+
+    // every item in the grid, row by row
+    pub fn items(&self) -> Vec<Item> {
+        self.cells.iter().filter_map(|&cell| cell).collect()
+    }
+
+    // the smallest rectangle holding every item, as x, y, width and height
+    fn bounds(&self) -> Option<(usize, usize, usize, usize)> {
+        let filled: Vec<(usize, usize)> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.get(x, y).is_some())
+            .collect();
+        if filled.is_empty() {
+            return None;
+        }
+        let min_x = filled.iter().map(|&(x, _)| x).min().unwrap();
+        let max_x = filled.iter().map(|&(x, _)| x).max().unwrap();
+        let min_y = filled.iter().map(|&(_, y)| y).min().unwrap();
+        let max_y = filled.iter().map(|&(_, y)| y).max().unwrap();
+        Some((min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+}
+
+impl Recipe {
+    pub fn matches(&self, grid: &Grid) -> bool {
+        match self.pattern {
+            Pattern::Shaped { width, height, ref cells } => {
+                // empty rows and columns around the items don't matter
+                let (x0, y0, w, h) = match grid.bounds() {
+                    Some(bounds) => bounds,
+                    None => return false,
+                };
+                if w != width || h != height {
+                    return false;
+                }
+                let fits = |mirrored: bool| (0..height).all(|y| (0..width).all(|x| {
+                    let px = if mirrored { width - 1 - x } else { x };
+                    grid.get(x0 + x, y0 + y) == cells[y * width + px]
+                }));
+                fits(false) || fits(true)
+            },
+            Pattern::Shapeless(ref ingredients) => {
+                let mut left = ingredients.clone();
+                for item in grid.items() {
+                    match left.iter().position(|&i| i == item) {
+                        Some(i) => { left.swap_remove(i); },
+                        None => return false,
+                    }
+                }
+                left.is_empty()
+            },
+        }
+    }
+
+    // one ingredient per cell, in the upper left corner of a grid of the given size,
+    // None if the recipe doesn't fit
+    pub fn grid(&self, width: usize, height: usize) -> Option<Grid> {
+        let mut grid = Grid::new(width, height);
+        match self.pattern {
+            Pattern::Shaped { width: w, height: h, ref cells } => {
+                if w > width || h > height {
+                    return None;
+                }
+                for y in 0..h {
+                    for x in 0..w {
+                        grid.set(x, y, cells[y * w + x]);
+                    }
+                }
+            },
+            Pattern::Shapeless(ref ingredients) => {
+                if ingredients.len() > width * height {
+                    return None;
+                }
+                for (i, &item) in ingredients.iter().enumerate() {
+                    grid.set(i % width, i / width, Some(item));
+                }
+            },
+        }
+        Some(grid)
+    }
+}
+
+// the first recipe that matches the grid
+pub fn find<'a>(recipes: &'a [Recipe], grid: &Grid) -> Option<&'a Recipe> {
+    recipes.iter().find(|recipe| recipe.matches(grid))
+}
+
+// tries the recipe file at path first and falls back to the embedded recipes,
+// the error says why the file wasn't used
+pub fn load(path: &Path) -> (Vec<Recipe>, Option<String>) {
+    let mut text = String::new();
+    let error = match File::open(path).and_then(|mut file| file.read_to_string(&mut text)) {
+        Ok(_) => match parse(&text) {
+            Ok(recipes) => return (recipes, None),
+            Err(e) => format!("{}:{}", path.display(), e),
+        },
+        Err(e) => format!("Could not read {}: {}", path.display(), e),
+    };
+    let recipes = parse(DEFAULT_RECIPES).unwrap_or(Vec::new());
+    (recipes, Some(format!("{}, using the embedded recipes", error)))
+}
+
+// parses a whole recipe file, up to the first broken line
+pub fn parse(text: &str) -> Result<Vec<Recipe>, LineError> {
+    let mut recipes = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_recipe(line) {
+            Ok(recipe) => recipes.push(recipe),
+            Err(e) => return Err(LineError::new(n + 1, e)),
+        }
+    }
+    Ok(recipes)
+}
+
+fn parse_recipe(line: &str) -> Result<Recipe, String> {
+    let mut sides = line.splitn(2, '=');
+    let output: Vec<&str> = sides.next().unwrap_or("").split_whitespace().collect();
+    let mut input = match sides.next() {
+        Some(input) => input.split_whitespace(),
+        None => return Err(format!("expected `output = pattern`, found `{}`", line)),
+    };
+    if output.len() > 2 {
+        return Err(format!("expected `<output> [count]`, found `{}`", output.join(" ")));
+    }
+    let item = try!(parse_item(output.get(0).cloned().unwrap_or("")));
+    let count = match output.get(1) {
+        Some(count) => try!(count.parse().map_err(|_| format!("invalid count `{}`", count))),
+        None => 1,
+    };
+    if count == 0 {
+        return Err("the count has to be at least 1".to_string());
+    }
+    let pattern = match input.next() {
+        Some("shaped") => {
+            let rest: Vec<&str> = input.collect();
+            let rest = rest.join(" ");
+            let mut rows = Vec::new();
+            for row in rest.split('/') {
+                let cells = try!(row.split_whitespace().map(|cell| match cell {
+                    "_" => Ok(None),
+                    name => parse_item(name).map(Some),
+                }).collect::<Result<Vec<_>, String>>());
+                rows.push(cells);
+            }
+            let width = rows[0].len();
+            if width == 0 || rows.iter().any(|row| row.len() != width) {
+                return Err("every row needs the same number of cells".to_string());
+            }
+            // the grid gets trimmed the same way before matching
+            let filled = |y: usize, x: usize| rows[y][x].is_some();
+            let ys: Vec<usize> = (0..rows.len()).filter(|&y| (0..width).any(|x| filled(y, x))).collect();
+            let xs: Vec<usize> = (0..width).filter(|&x| (0..rows.len()).any(|y| filled(y, x))).collect();
+            let (y0, x0) = match (ys.first(), xs.first()) {
+                (Some(&y0), Some(&x0)) => (y0, x0),
+                _ => return Err("a shaped recipe needs ingredients".to_string()),
+            };
+            let (height, width) = (ys[ys.len() - 1] - y0 + 1, xs[xs.len() - 1] - x0 + 1);
+            Pattern::Shaped {
+                width: width,
+                height: height,
+                cells: (y0..y0 + height).flat_map(|y| rows[y][x0..x0 + width].iter().cloned()).collect(),
+            }
+        },
+        Some("shapeless") => {
+            let ingredients = try!(input.map(parse_item).collect::<Result<Vec<_>, String>>());
+            if ingredients.is_empty() {
+                return Err("a shapeless recipe needs ingredients".to_string());
+            }
+            Pattern::Shapeless(ingredients)
+        },
+        Some(kind) => return Err(format!("expected `shaped` or `shapeless`, found `{}`", kind)),
+        None => return Err("missing pattern".to_string()),
+    };
+    Ok(Recipe {
+        pattern: pattern,
+        output: item,
+        count: count,
+    })
+}
+
+fn parse_item(name: &str) -> Result<Item, String> {
+    Item::from_name(name).ok_or_else(|| format!("unknown item `{}`", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use logic::block::Block;
+    use logic::item::{ Item, Tool };
+    use super::*;
+
+    const STONE: Item = Item::Block(Block::Stone);
+    const LOG: Item = Item::Block(Block::Log);
+    const SAND: Item = Item::Block(Block::Sand);
+    const DIRT: Item = Item::Block(Block::Dirt);
+
+    fn recipe(line: &str) -> Recipe {
+        parse(line).unwrap().pop().unwrap()
+    }
+
+    // rows of cells like in the recipe file
+    fn grid(rows: &[&[Option<Item>]]) -> Grid {
+        let mut grid = Grid::new(3, 3);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                grid.set(x, y, cell);
+            }
+        }
+        grid
+    }
+
+    fn parse_error(text: &str) -> LineError {
+        parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn the_embedded_recipes_parse() {
+        let recipes = parse(DEFAULT_RECIPES).unwrap();
+        assert!(!recipes.is_empty());
+    }
+
+    #[test]
+    fn recipes_parse_with_counts_and_empty_cells() {
+        let recipes = parse("# comment\n\naxe = shaped stone stone / stone log / _ log\nslab 6 = shaped stone stone stone\nglass = shapeless sand sand\n").unwrap();
+        assert_eq!(recipes, vec![
+            Recipe {
+                pattern: Pattern::Shaped { width: 2, height: 3, cells: vec![Some(STONE), Some(STONE), Some(STONE), Some(LOG), None, Some(LOG)] },
+                output: Item::Tool(Tool::Axe),
+                count: 1,
+            },
+            Recipe {
+                pattern: Pattern::Shaped { width: 3, height: 1, cells: vec![Some(STONE); 3] },
+                output: Item::Block(Block::Slab),
+                count: 6,
+            },
+            Recipe {
+                pattern: Pattern::Shapeless(vec![SAND, SAND]),
+                output: Item::Block(Block::Glass),
+                count: 1,
+            },
+        ]);
+    }
+
+    #[test]
+    fn bad_counts_are_refused() {
+        assert_eq!(parse_error("slab x = shaped stone").message, "invalid count `x`");
+        assert_eq!(parse_error("slab 0 = shaped stone").message, "the count has to be at least 1");
+        assert_eq!(parse_error("slab 300 = shaped stone").message, "invalid count `300`");
+    }
+
+    #[test]
+    fn uneven_rows_are_refused() {
+        assert_eq!(parse_error("axe = shaped stone stone / log").message, "every row needs the same number of cells");
+        assert_eq!(parse_error("axe = shaped stone / / log").message, "every row needs the same number of cells");
+        assert_eq!(parse_error("axe = shaped").message, "every row needs the same number of cells");
+    }
+
+    #[test]
+    fn empty_border_cells_are_trimmed() {
+        let expected = Pattern::Shaped { width: 1, height: 1, cells: vec![Some(STONE)] };
+        assert_eq!(recipe("dirt = shaped _ stone").pattern, expected);
+        assert_eq!(recipe("dirt = shaped stone / _").pattern, expected);
+        assert_eq!(recipe("dirt = shaped _ _ _ / _ stone _ / _ _ _").pattern, expected);
+        // empty cells between the items stay
+        assert_eq!(recipe("dirt = shaped stone _ / _ stone / _ _").pattern,
+                   Pattern::Shaped { width: 2, height: 2, cells: vec![Some(STONE), None, None, Some(STONE)] });
+        assert!(recipe("dirt = shaped _ stone").matches(&grid(&[&[None, None, Some(STONE)]])));
+        assert!(recipe("dirt = shaped stone / _").matches(&grid(&[&[None], &[None], &[Some(STONE)]])));
+    }
+
+    #[test]
+    fn shaped_recipes_need_ingredients() {
+        assert_eq!(parse_error("dirt = shaped _").message, "a shaped recipe needs ingredients");
+        assert_eq!(parse_error("dirt = shaped _ _ / _ _").message, "a shaped recipe needs ingredients");
+    }
+
+    #[test]
+    fn unknown_items_are_refused() {
+        assert_eq!(parse_error("diamond = shaped stone").message, "unknown item `diamond`");
+        assert_eq!(parse_error("axe = shaped stone diamond").message, "unknown item `diamond`");
+        assert_eq!(parse_error("glass = shapeless sand diamond").message, "unknown item `diamond`");
+        assert_eq!(parse_error("= shapeless sand").message, "unknown item ``");
+    }
+
+    #[test]
+    fn the_equals_sign_and_pattern_are_required() {
+        assert_eq!(parse_error("glass shapeless sand sand").message, "expected `output = pattern`, found `glass shapeless sand sand`");
+        assert_eq!(parse_error("glass 2 sand = shapeless sand").message, "expected `<output> [count]`, found `glass 2 sand`");
+        assert_eq!(parse_error("glass =").message, "missing pattern");
+        assert_eq!(parse_error("glass = round sand").message, "expected `shaped` or `shapeless`, found `round`");
+        assert_eq!(parse_error("glass = shapeless").message, "a shapeless recipe needs ingredients");
+    }
+
+    #[test]
+    fn errors_carry_their_line_number() {
+        let e = parse_error("# comment\n\nglass = shapeless sand sand\ndirt = shapeless\n");
+        assert_eq!(e.line, 4);
+    }
+
+    #[test]
+    fn shaped_recipes_match_at_every_offset() {
+        let single = recipe("dirt = shaped sand");
+        let square = recipe("stone = shaped sand sand / sand dirt");
+        for y in 0..3 {
+            for x in 0..3 {
+                let mut g = Grid::new(3, 3);
+                g.set(x, y, Some(SAND));
+                assert!(single.matches(&g), "1x1 at {} {}", x, y);
+                assert!(!square.matches(&g));
+            }
+        }
+        for y in 0..2 {
+            for x in 0..2 {
+                let mut g = Grid::new(3, 3);
+                g.set(x, y, Some(SAND));
+                g.set(x + 1, y, Some(SAND));
+                g.set(x, y + 1, Some(SAND));
+                g.set(x + 1, y + 1, Some(DIRT));
+                assert!(square.matches(&g), "2x2 at {} {}", x, y);
+                assert!(!single.matches(&g));
+            }
+        }
+    }
+
+    #[test]
+    fn extra_items_spoil_a_shaped_recipe() {
+        let shovel = recipe("shovel = shaped stone / log / log");
+        assert!(shovel.matches(&grid(&[&[None, Some(STONE)], &[None, Some(LOG)], &[None, Some(LOG)]])));
+        assert!(!shovel.matches(&grid(&[&[Some(DIRT), Some(STONE)], &[None, Some(LOG)], &[None, Some(LOG)]])));
+        assert!(!shovel.matches(&grid(&[&[Some(STONE)], &[Some(LOG)], &[Some(STONE)]])));
+    }
+
+    #[test]
+    fn shaped_recipes_match_mirrored() {
+        let axe = recipe("axe = shaped stone stone / stone log / _ log");
+        let s = Some(STONE);
+        let l = Some(LOG);
+        assert!(axe.matches(&grid(&[&[s, s], &[s, l], &[None, l]])));
+        // left and right swapped
+        assert!(axe.matches(&grid(&[&[s, s], &[l, s], &[l, None]])));
+        assert!(axe.matches(&grid(&[&[None, s, s], &[None, l, s], &[None, l, None]])));
+    }
+
+    #[test]
+    fn asymmetric_recipes_only_match_mirrored_left_and_right() {
+        let axe = recipe("axe = shaped stone stone / stone log / _ log");
+        let s = Some(STONE);
+        let l = Some(LOG);
+        // upside down
+        assert!(!axe.matches(&grid(&[&[None, l], &[s, l], &[s, s]])));
+        // upside down and mirrored
+        assert!(!axe.matches(&grid(&[&[l, None], &[l, s], &[s, s]])));
+        // turned a quarter to the left and to the right
+        assert!(!axe.matches(&grid(&[&[s, l, l], &[s, s, None]])));
+        assert!(!axe.matches(&grid(&[&[None, s, s], &[l, l, s]])));
+    }
+
+    #[test]
+    fn shapeless_recipes_count_duplicate_ingredients() {
+        let glass = recipe("glass = shapeless sand sand");
+        let mut g = Grid::new(3, 3);
+        g.set(2, 0, Some(SAND));
+        assert!(!glass.matches(&g));
+        g.set(0, 2, Some(SAND));
+        assert!(glass.matches(&g));
+        g.set(1, 1, Some(SAND));
+        assert!(!glass.matches(&g));
+        g.set(1, 1, Some(DIRT));
+        assert!(!glass.matches(&g));
+
+        let mixed = recipe("dirt 2 = shapeless sand dirt sand");
+        let mut g = Grid::new(3, 3);
+        g.set(0, 0, Some(DIRT));
+        g.set(1, 0, Some(SAND));
+        assert!(!mixed.matches(&g));
+        g.set(2, 2, Some(SAND));
+        assert!(mixed.matches(&g));
+    }
+
+    #[test]
+    fn nothing_matches_an_empty_grid() {
+        let recipes = parse(DEFAULT_RECIPES).unwrap();
+        assert!(recipes.iter().all(|recipe| !recipe.matches(&Grid::new(3, 3))));
+        assert!(find(&recipes, &Grid::new(3, 3)).is_none());
+        assert!(find(&recipes, &Grid::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn recipes_match_their_own_grid() {
+        let recipes = parse(DEFAULT_RECIPES).unwrap();
+        for recipe in &recipes {
+            let grid = recipe.grid(3, 3).unwrap();
+            assert_eq!(find(&recipes, &grid).map(|found| found.output), Some(recipe.output));
+        }
+    }
+
+    #[test]
+    fn a_missing_file_falls_back_to_the_embedded_recipes() {
+        let (recipes, error) = load(Path::new("does/not/exist/recipes.txt"));
+        assert_eq!(recipes, parse(DEFAULT_RECIPES).unwrap());
+        assert!(error.is_some());
+    }
+}
@@ -0,0 +1,536 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::f32::consts::PI;
+use std::mem;
+
+use cgmath::{ Point3, Vector3 };
+
+use types::Aabb;
+use super::item::Item;
+use super::chunks::{ Chunks, ChunkPos };
+use super::events::GameEvent;
+use super::noise;
+
+pub type EntityId = u32;
+
+// seconds per tick of the simulation clock
+pub const TICK: f32 = 0.05;
+// in blocks per second squared
+pub const GRAVITY: f32 = 24.0;
+// part of the horizontal velocity kept per tick on the ground
+const FRICTION: f32 = 0.6;
+// in blocks per second
+const MOB_SPEED: f32 = 1.5;
+const JUMP_SPEED: f32 = 7.0;
+// mobs decide where to go next every this many ticks
+const MOB_THINK_TICKS: u64 = 60;
+const PROJECTILE_LIFETIME: u32 = 200;
+// dropped items disappear after five minutes
+const ITEM_LIFETIME: u32 = 6000;
+// items can't be picked up right after they were dropped
+pub const PICKUP_DELAY: u32 = 10;
+// items of the same kind closer than this become one stack
+const MERGE_RADIUS: f32 = 0.5;
+
+// the kind specific part of an entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    // walks around aimlessly, heading is an angle in radians
+    Mob {
+        walking: bool,
+        heading: f32,
+    },
+    // a stack of items lying around, age in ticks
+    Item {
+        item: Item,
+        count: u8,
+        age: u32,
+    },
+    // flies until it hits something, age in ticks
+    Projectile {
+        age: u32,
+    },
+}
+
+impl Kind {
+    // extent of the bounding box
+    pub fn size(self) -> Vector3<f32> {
+        match self {
+            Kind::Mob { .. }        => Vector3::new(0.6, 0.9, 0.6),
+            Kind::Item { .. }       => Vector3::new(0.25, 0.25, 0.25),
+            Kind::Projectile { .. } => Vector3::new(0.2, 0.2, 0.2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Entity {
+    // world coordinates of the bottom center of the bounding box
+    pub pos: Point3<f32>,
+    // in blocks per second
+    pub velocity: Vector3<f32>,
+    pub size: Vector3<f32>,
+    pub on_ground: bool,
+    pub kind: Kind,
+}
+
+impl Entity {
+    pub fn new(kind: Kind, pos: Point3<f32>, velocity: Vector3<f32>) -> Entity {
+        Entity {
+            pos: pos,
+            velocity: velocity,
+            size: kind.size(),
+            on_ground: false,
+            kind: kind,
+        }
+    }
+
+    pub fn bbox(&self) -> Aabb {
+        let (x, z) = (self.size.x / 2.0, self.size.z / 2.0);
+        Aabb::new(
+            Point3::new(self.pos.x - x, self.pos.y, self.pos.z - z),
+            Point3::new(self.pos.x + x, self.pos.y + self.size.y, self.pos.z + z),
+        )
+    }
+
+    // the chunk the entity is saved with
+    pub fn chunk(&self) -> ChunkPos {
+        Point3::new(
+            (self.pos.x / 16.0).floor() as i32,
+            (self.pos.y / 16.0).floor() as i32,
+            (self.pos.z / 16.0).floor() as i32,
+        )
+    }
+
+    // kind tag, position, velocity and the kind specific fields, little endian
+    pub fn to_bytes(&self, bytes: &mut Vec<u8>) {
+        let tag = match self.kind {
+            Kind::Mob { .. }        => 0,
+            Kind::Item { .. }       => 1,
+            Kind::Projectile { .. } => 2,
+        };
+        bytes.push(tag);
+        for &v in &[self.pos.x, self.pos.y, self.pos.z, self.velocity.x, self.velocity.y, self.velocity.z] {
+            put_f32(bytes, v);
+        }
+        match self.kind {
+            Kind::Mob { walking, heading } => {
+                bytes.push(walking as u8);
+                put_f32(bytes, heading);
+            },
+            Kind::Item { item, count, age } => {
+                bytes.push(item.id());
+                bytes.push(count);
+                put_u32(bytes, age);
+            },
+            Kind::Projectile { age } => put_u32(bytes, age),
+        }
+    }
+
+    // reads one entity starting at *i and moves i past it, None if the bytes are invalid
+    pub fn from_bytes(bytes: &[u8], i: &mut usize) -> Option<Entity> {
+        // every kind has a fixed length, including the tag
+        let len = match bytes.get(*i) {
+            Some(&0) => 30,
+            Some(&1) => 31,
+            Some(&2) => 29,
+            _ => return None,
+        };
+        if *i + len > bytes.len() {
+            return None;
+        }
+        let tag = get_u8(bytes, i);
+        let mut v = [0.0; 6];
+        for x in v.iter_mut() {
+            *x = get_f32(bytes, i);
+        }
+        let kind = match tag {
+            0 => Kind::Mob {
+                walking: get_u8(bytes, i) != 0,
+                heading: get_f32(bytes, i),
+            },
+            1 => {
+                let item = match Item::from_id(get_u8(bytes, i)) {
+                    Some(item) => item,
+                    None => return None,
+                };
+                let count = get_u8(bytes, i);
+                // merging and picking up rely on the count fitting into a stack
+                if count == 0 || count > item.max_stack() {
+                    return None;
+                }
+                Kind::Item { item: item, count: count, age: get_u32(bytes, i) }
+            },
+            _ => Kind::Projectile {
+                age: get_u32(bytes, i),
+            },
+        };
+        Some(Entity::new(kind, Point3::new(v[0], v[1], v[2]), Vector3::new(v[3], v[4], v[5])))
+    }
+}
+
+// every dynamic object in the loaded part of the world
+#[derive(Debug)]
+pub struct Entities {
+    entities: HashMap<EntityId, Entity>,
+    next_id: EntityId,
+    // spawned and removed since the last call to take_events, in order
+    events: Vec<GameEvent>,
+}
+
+impl Entities {
+    pub fn new() -> Entities {
+        Entities {
+            entities: HashMap::new(),
+            next_id: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    // ids are only unique while the world is open, they aren't saved
+    pub fn spawn(&mut self, entity: Entity) -> EntityId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entities.insert(id, entity);
+        self.events.push(GameEvent::EntitySpawned(id));
+        id
+    }
+
+    pub fn remove(&mut self, id: EntityId) -> Option<Entity> {
+        let entity = self.entities.remove(&id);
+        if entity.is_some() {
+            self.events.push(GameEvent::EntityRemoved(id));
+        }
+        entity
+    }
+
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(&id)
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<EntityId, Entity> {
+        self.entities.iter()
+    }
+
+    // entities whose bounding box intersects the region, in order of their ids
+    pub fn in_region(&self, region: &Aabb) -> Vec<EntityId> {
+        let mut ids: Vec<EntityId> = self.entities.iter()
+            .filter(|&(_, e)| e.bbox().intersects(region))
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    // item and count if the entity is a dropped item
+    pub fn item(&self, id: EntityId) -> Option<(Item, u8)> {
+        match self.entities.get(&id).map(|e| e.kind) {
+            Some(Kind::Item { item, count, .. }) => Some((item, count)),
+            _ => None,
+        }
+    }
+
+    // an item with a count of zero is removed
+    pub fn set_item_count(&mut self, id: EntityId, count: u8) {
+        if count == 0 {
+            self.remove(id);
+            return;
+        }
+        if let Some(entity) = self.entities.get_mut(&id) {
+            if let Kind::Item { item, age, .. } = entity.kind {
+                entity.kind = Kind::Item { item: item, count: count, age: age };
+            }
+        }
+    }
+
+    pub fn in_chunk(&self, pos: ChunkPos) -> Vec<Entity> {
+        self.entities.values().filter(|e| e.chunk() == pos).cloned().collect()
+    }
+
+    // removes the entities of a chunk that gets unloaded, in order of their ids,
+    // the caller publishes that they are gone
+    pub fn take_chunk(&mut self, pos: ChunkPos) -> Vec<EntityId> {
+        let mut ids: Vec<EntityId> = self.entities.iter()
+            .filter(|&(_, e)| e.chunk() == pos)
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort();
+        for id in &ids {
+            self.entities.remove(id);
+        }
+        ids
+    }
+
+    // advances every entity by one tick, entities in unloaded chunks stay frozen
+    pub fn tick(&mut self, chunks: &Chunks, tick: u64) {
+        let mut ids: Vec<EntityId> = self.entities.keys().cloned().collect();
+        // same order every time, so the simulation doesn't depend on the hash map
+        ids.sort();
+        for id in ids {
+            let alive = {
+                let entity = self.entities.get_mut(&id).unwrap();
+                if !chunks.is_loaded(entity.chunk()) {
+                    continue;
+                }
+                update(id, entity, chunks, tick)
+            };
+            if !alive {
+                self.remove(id);
+            }
+        }
+        self.merge_items();
+    }
+
+    // nearby stacks of the same item get combined, the one with the lower id takes them in
+    fn merge_items(&mut self) {
+        let mut ids: Vec<EntityId> = self.entities.keys().cloned().filter(|&id| self.item(id).is_some()).collect();
+        ids.sort();
+        for id in ids {
+            let region = match self.entities.get(&id) {
+                Some(entity) => entity.bbox().grow(MERGE_RADIUS),
+                None => continue,
+            };
+            for other in self.in_region(&region) {
+                if other <= id {
+                    continue;
+                }
+                let (item, count, other_item, other_count) = match (self.item(id), self.item(other)) {
+                    (Some((i, c)), Some((oi, oc))) => (i, c, oi, oc),
+                    _ => continue,
+                };
+                if item != other_item {
+                    continue;
+                }
+                let moved = cmp::min(other_count, item.max_stack().saturating_sub(count));
+                self.set_item_count(id, count + moved);
+                self.set_item_count(other, other_count - moved);
+            }
+        }
+    }
+}
+
+// false if the entity is gone
+fn update(id: EntityId, entity: &mut Entity, chunks: &Chunks, tick: u64) -> bool {
+    match entity.kind {
+        Kind::Mob { mut walking, mut heading } => {
+            // everyone thinks at a different time
+            if (tick + id as u64) % MOB_THINK_TICKS == 0 {
+                let seed = noise::hash(tick, id as i32, 0, 0);
+                walking = noise::hash_unit(seed, 0, 0, 0) < 0.6;
+                heading = noise::hash_unit(seed, 1, 0, 0) * 2.0 * PI;
+                entity.kind = Kind::Mob { walking: walking, heading: heading };
+            }
+            if entity.on_ground {
+                let speed = if walking { MOB_SPEED } else { 0.0 };
+                entity.velocity.x = heading.cos() * speed;
+                entity.velocity.z = heading.sin() * speed;
+            }
+        },
+        Kind::Item { item, count, age } => {
+            if age >= ITEM_LIFETIME {
+                return false;
+            }
+            entity.kind = Kind::Item { item: item, count: count, age: age + 1 };
+        },
+        Kind::Projectile { age } => {
+            if age >= PROJECTILE_LIFETIME {
+                return false;
+            }
+            entity.kind = Kind::Projectile { age: age + 1 };
+        },
+    }
+
+    entity.velocity.y -= GRAVITY * TICK;
+    let wanted = entity.velocity;
+    let (offset, on_ground, hit) = move_box(chunks, &entity.bbox(), &mut entity.velocity);
+    entity.pos = entity.pos + offset;
+    entity.on_ground = on_ground;
+
+    match entity.kind {
+        // blocked while walking, try to jump over it
+        Kind::Mob { walking: true, .. } if entity.on_ground
+            && (entity.velocity.x != wanted.x || entity.velocity.z != wanted.z) => {
+            entity.velocity.y = JUMP_SPEED;
+        },
+        Kind::Projectile { .. } if hit => return false,
+        Kind::Item { .. } if entity.on_ground => {
+            entity.velocity.x *= FRICTION;
+            entity.velocity.z *= FRICTION;
+        },
+        _ => {},
+    }
+    true
+}
+
+// moves a box by one tick of velocity, one axis after the other so it slides along walls,
+// the velocity along blocked axes drops to zero,
+// returns how far it got, whether it landed on something and whether it hit anything
+pub fn move_box(chunks: &Chunks, bbox: &Aabb, velocity: &mut Vector3<f32>) -> (Vector3<f32>, bool, bool) {
+    let delta = *velocity * TICK;
+    let solids = solid_boxes(chunks, &bbox.expand(delta));
+    let mut offset = Vector3::new(0.0, 0.0, 0.0);
+    let mut hit = false;
+    let mut on_ground = false;
+    // vertical first, so falling boxes land before they slide
+    for &axis in &[1, 0, 2] {
+        let moved = bbox.offset(offset);
+        let d = solids.iter().fold(delta[axis], |d, solid| moved.clip(solid, axis, d));
+        if d != delta[axis] {
+            hit = true;
+            velocity[axis] = 0.0;
+            if axis == 1 && delta[axis] < 0.0 {
+                on_ground = true;
+            }
+        }
+        offset[axis] = d;
+    }
+    (offset, on_ground, hit)
+}
+
+// collision boxes of all blocks touching the region, in world coordinates,
+// unloaded chunks are solid so nothing wanders off into them
+fn solid_boxes(chunks: &Chunks, region: &Aabb) -> Vec<Aabb> {
+    let mut boxes = Vec::new();
+    let (min, max) = (region.min, region.max);
+    for x in min.x.floor() as i32..max.x.floor() as i32 + 1 {
+        for y in min.y.floor() as i32..max.y.floor() as i32 + 1 {
+            for z in min.z.floor() as i32..max.z.floor() as i32 + 1 {
+                let (chunk, block) = split(Point3::new(x, y, z));
+                let offset = Vector3::new(x as f32, y as f32, z as f32);
+                if !chunks.is_loaded(chunk) {
+                    boxes.push(Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0)).offset(offset));
+                    continue;
+                }
+                let state = chunks[chunk].state(block);
+                for b in chunks[chunk][block].collision_boxes(state) {
+                    boxes.push(b.offset(offset));
+                }
+            }
+        }
+    }
+    boxes
+}
+
+// chunk and position inside of it of a world block position
+pub fn split(pos: Point3<i32>) -> (ChunkPos, Point3<u8>) {
+    let chunk = |v: i32| if v >= 0 { v / 16 } else { (v + 1) / 16 - 1 };
+    let c = Point3::new(chunk(pos.x), chunk(pos.y), chunk(pos.z));
+    (c, Point3::new((pos.x - c.x * 16) as u8, (pos.y - c.y * 16) as u8, (pos.z - c.z * 16) as u8))
+}
+
+fn put_u32(bytes: &mut Vec<u8>, v: u32) {
+    for i in 0..4 {
+        bytes.push((v >> (i * 8)) as u8);
+    }
+}
+
+fn put_f32(bytes: &mut Vec<u8>, v: f32) {
+    put_u32(bytes, v.to_bits());
+}
+
+// the readers expect the length to be checked already
+fn get_u8(bytes: &[u8], i: &mut usize) -> u8 {
+    *i += 1;
+    bytes[*i - 1]
+}
+
+fn get_u32(bytes: &[u8], i: &mut usize) -> u32 {
+    let v = (0..4).fold(0, |v, j| v | (bytes[*i + j] as u32) << (j * 8));
+    *i += 4;
+    v
+}
+
+fn get_f32(bytes: &[u8], i: &mut usize) -> f32 {
+    f32::from_bits(get_u32(bytes, i))
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{ Point3, Vector3 };
+
+    use logic::block::Block;
+    use logic::item::{ Item, Tool };
+    use super::*;
+
+    #[test]
+    fn entities_survive_the_round_trip() {
+        let kinds = [
+            Kind::Mob { walking: true, heading: -1.25 },
+            Kind::Item { item: Item::Block(Block::Sand), count: 17, age: 123456 },
+            Kind::Projectile { age: 7 },
+        ];
+        let mut bytes = Vec::new();
+        for &kind in kinds.iter() {
+            Entity::new(kind, Point3::new(-16.5, 80.0, 1e-3), Vector3::new(0.0, -9.5, 3.0)).to_bytes(&mut bytes);
+        }
+        let mut i = 0;
+        for &kind in kinds.iter() {
+            let entity = Entity::from_bytes(&bytes, &mut i).unwrap();
+            assert_eq!(entity.kind, kind);
+            assert_eq!(entity.pos, Point3::new(-16.5, 80.0, 1e-3));
+            assert_eq!(entity.velocity, Vector3::new(0.0, -9.5, 3.0));
+        }
+        assert_eq!(i, bytes.len());
+    }
+
+    #[test]
+    fn floats_keep_every_bit() {
+        let mut bytes = Vec::new();
+        for &v in [0.0, -0.0, 1.5, -3.75e7, ::std::f32::MAX, ::std::f32::MIN_POSITIVE].iter() {
+            bytes.clear();
+            put_f32(&mut bytes, v);
+            assert_eq!(bytes.len(), 4);
+            let mut i = 0;
+            assert_eq!(get_f32(&bytes, &mut i).to_bits(), v.to_bits());
+        }
+    }
+
+    #[test]
+    fn truncated_entities_are_refused() {
+        let mut bytes = Vec::new();
+        Entity::new(Kind::Projectile { age: 1 }, Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)).to_bytes(&mut bytes);
+        bytes.pop();
+        assert!(Entity::from_bytes(&bytes, &mut 0).is_none());
+        assert!(Entity::from_bytes(&[9], &mut 0).is_none());
+    }
+
+    #[test]
+    fn item_counts_have_to_fit_a_stack() {
+        let item = |item, count| {
+            let mut bytes = Vec::new();
+            let kind = Kind::Item { item: item, count: count, age: 0 };
+            Entity::new(kind, Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 0.0)).to_bytes(&mut bytes);
+            Entity::from_bytes(&bytes, &mut 0)
+        };
+        assert!(item(Item::Block(Block::Sand), 0).is_none());
+        assert!(item(Item::Block(Block::Sand), 64).is_some());
+        assert!(item(Item::Block(Block::Sand), 65).is_none());
+        assert!(item(Item::Tool(Tool::Axe), 1).is_some());
+        assert!(item(Item::Tool(Tool::Axe), 2).is_none());
+    }
+
+    #[test]
+    fn overfull_items_dont_break_merging() {
+        let mut entities = Entities::new();
+        let axe = Item::Tool(Tool::Axe);
+        let pos = Point3::new(0.5, 0.0, 0.5);
+        let a = entities.spawn(Entity::new(Kind::Item { item: axe, count: 2, age: 0 }, pos, Vector3::new(0.0, 0.0, 0.0)));
+        let b = entities.spawn(Entity::new(Kind::Item { item: axe, count: 1, age: 0 }, pos, Vector3::new(0.0, 0.0, 0.0)));
+        entities.merge_items();
+        assert_eq!(entities.item(a), Some((axe, 2)));
+        assert_eq!(entities.item(b), Some((axe, 1)));
+    }
+}
@@ -0,0 +1,118 @@
+use std::mem;
+
+use cgmath::Point3;
+
+use super::block::Block;
+use super::chunks::ChunkPos;
+use super::entity::EntityId;
+
+// above this many published events nobody took, they are replaced by GameEvent::Resync
+const MAX_PUBLISHED: usize = 65536;
+
+// something that changed in the world, see EventBus
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    ChunkLoaded(ChunkPos),
+    // pos is in world coordinates
+    BlockChanged { pos: Point3<i32>, old: Block, new: Block },
+    // entities loaded with their chunk count as spawned too
+    EntitySpawned(EntityId),
+    // despawned, picked up or merged, not unloaded with a chunk
+    EntityRemoved(EntityId),
+    // saved and taken out of the world with its chunk, it comes back with a new id
+    EntityUnloaded(EntityId),
+    ChunkUnloaded(ChunkPos),
+    // earlier events were dropped because nobody took them,
+    // whoever keeps a copy of the world has to build it again from scratch
+    Resync,
+}
+
+// collects the events of a tick and hands them out together at its end,
+// so whoever listens sees the world as it is after the whole tick
+#[derive(Debug)]
+pub struct EventBus {
+    // events of the tick in progress
+    pending: Vec<GameEvent>,
+    // events of finished ticks, until they get taken
+    published: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus {
+            pending: Vec::new(),
+            published: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: GameEvent) {
+        self.pending.push(event);
+    }
+
+    // ends the tick, the events stay in the order they were pushed in,
+    // so a chunk unloaded and loaded again within a tick ends up loaded
+    pub fn flush(&mut self) -> Vec<GameEvent> {
+        let events = mem::replace(&mut self.pending, Vec::new());
+        self.published.extend(events.iter().cloned());
+        if self.published.len() > MAX_PUBLISHED {
+            self.published.clear();
+            self.published.push(GameEvent::Resync);
+        }
+        events
+    }
+
+    // the events of all ticks finished since the last call, tick by tick
+    pub fn take(&mut self) -> Vec<GameEvent> {
+        mem::replace(&mut self.published, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use super::*;
+
+    #[test]
+    fn events_keep_the_order_they_were_pushed_in() {
+        let pos = Point3::new(0, 0, 0);
+        let mut bus = EventBus::new();
+        bus.push(GameEvent::EntityRemoved(1));
+        bus.push(GameEvent::ChunkUnloaded(pos));
+        bus.push(GameEvent::ChunkLoaded(pos));
+        bus.push(GameEvent::EntitySpawned(2));
+        let expected = vec![
+            GameEvent::EntityRemoved(1),
+            GameEvent::ChunkUnloaded(pos),
+            GameEvent::ChunkLoaded(pos),
+            GameEvent::EntitySpawned(2),
+        ];
+        assert_eq!(bus.flush(), expected);
+        bus.push(GameEvent::ChunkUnloaded(pos));
+        bus.flush();
+
+        let mut all = expected.clone();
+        all.push(GameEvent::ChunkUnloaded(pos));
+        assert_eq!(bus.take(), all);
+        assert!(bus.take().is_empty());
+    }
+
+    #[test]
+    fn too_many_events_turn_into_a_resync() {
+        let mut bus = EventBus::new();
+        for id in 0..MAX_PUBLISHED as u32 {
+            bus.push(GameEvent::EntitySpawned(id));
+        }
+        bus.flush();
+        assert_eq!(bus.take().len(), MAX_PUBLISHED);
+
+        for id in 0..MAX_PUBLISHED as u32 + 1 {
+            bus.push(GameEvent::EntitySpawned(id));
+        }
+        // the listeners of the tick still get all of them
+        assert_eq!(bus.flush().len(), MAX_PUBLISHED + 1);
+        bus.push(GameEvent::EntityRemoved(0));
+        bus.flush();
+        assert_eq!(bus.take(), vec![GameEvent::Resync, GameEvent::EntityRemoved(0)]);
+    }
+}
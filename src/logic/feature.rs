@@ -0,0 +1,89 @@
+use cgmath::Vector3;
+
+use super::biome::Biome;
+use super::block::Block;
+use super::noise::hash;
+
+// no feature reaches farther than this from its origin column,
+// so a chunk only has to look at origins this close to its border
+pub const MAX_REACH: i32 = 3;
+
+// decorations placed on top of the terrain
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    Tree {
+        height: i32,
+    },
+    Boulder {
+        radius: i32,
+    },
+    // a crumbled ring of stone walls
+    Ruin,
+}
+
+impl Feature {
+    // variation is any well mixed value, it has to be derived from the seed and position
+    pub fn choose(biome: Biome, variation: u64) -> Feature {
+        use super::biome::Biome::*;
+        let roll = variation % 100;
+        let size = (variation >> 8) as i32 & 0x3;
+        match biome {
+            Forest | Plains if roll < 90 => Feature::Tree { height: 4 + size },
+            Mountains if roll < 80 => Feature::Boulder { radius: 1 + size % 2 },
+            Desert if roll < 70 => Feature::Boulder { radius: 1 },
+            _ => Feature::Ruin,
+        }
+    }
+
+    // blocks relative to the origin, which is the air block right above the surface,
+    // variation decides which parts of a ruin are still standing
+    pub fn blocks(self, variation: u64) -> Vec<(Vector3<i32>, Block)> {
+        let mut blocks = Vec::new();
+        match self {
+            Feature::Tree { height } => {
+                for y in 0..height {
+                    blocks.push((Vector3::new(0, y, 0), Block::Log));
+                }
+                // two wide layers of leaves around the top of the trunk and a small one above
+                for y in height - 2..height + 1 {
+                    let r: i32 = if y < height { 2 } else { 1 };
+                    for x in -r..r + 1 {
+                        for z in -r..r + 1 {
+                            let corner = x.abs() == r && z.abs() == r;
+                            let trunk = x == 0 && z == 0 && y < height;
+                            if !corner && !trunk {
+                                blocks.push((Vector3::new(x, y, z), Block::Leaves));
+                            }
+                        }
+                    }
+                }
+            },
+            Feature::Boulder { radius } => {
+                for x in -radius..radius + 1 {
+                    for y in -1..radius + 1 {
+                        for z in -radius..radius + 1 {
+                            if x * x + y * y + z * z <= radius * radius + 1 {
+                                blocks.push((Vector3::new(x, y, z), Block::Stone));
+                            }
+                        }
+                    }
+                }
+            },
+            Feature::Ruin => {
+                for x in -MAX_REACH..MAX_REACH + 1 {
+                    for z in -MAX_REACH..MAX_REACH + 1 {
+                        if x.abs() != MAX_REACH && z.abs() != MAX_REACH {
+                            continue;
+                        }
+                        // every wall column has its own height, some are gone completely
+                        let height = (hash(variation, x, 0, z) % 4) as i32 - 1;
+                        for y in 0..height {
+                            blocks.push((Vector3::new(x, y, z), Block::Stone));
+                        }
+                    }
+                }
+            },
+        }
+        blocks
+    }
+}
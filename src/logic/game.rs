@@ -1,29 +1,374 @@
 
-use cgmath::{ Point, Point3 };
+use std::cell::RefCell;
+use std::mem;
+use std::path::{ Path, PathBuf };
+use std::rc::Rc;
 
-use types::Face;
-use super::chunks::{ Chunks, ChunkPos };
+use cgmath::{ Point, Point3, Vector3 };
+
+use config::Config;
+use types::{ Aabb, Face };
+use super::chunks::{ Chunks, ChunkPos, ChunkStats };
+use super::crafting::{ self, Grid, Recipe };
+use super::entity::{ self, Entities, Entity, EntityId, Kind };
+use super::events::{ EventBus, GameEvent };
+use super::noise;
+use super::player::{ GameMode, Player };
+use super::plugin::Plugin;
+use super::generator::Generator;
+use super::storage::Storage;
+use super::world::{ GameRules, WorldError, WorldInfo };
 use super::chunk::{ Chunk, BlockPos };
-use super::block::Block;
+use super::biome::Biome;
+use super::block::{ Block, BlockState };
+use super::item::{ Item, Tool };
+
+// in blocks per second
+const THROW_SPEED: f32 = 20.0;
+// items this close to the player get picked up
+const PICKUP_RADIUS: f32 = 1.0;
+// in chunks, closer ones are always loaded
+pub const MIN_VIEW_DIST: u8 = 2;
+// every chunk in range gets loaded right away, that is a cube of (2 * 12 - 1)^3, about 12000 chunks
+pub const MAX_VIEW_DIST: u8 = 12;
+// the player gets saved this often, a minute, so a crash doesn't lose much
+const SAVE_TICKS: u64 = 1200;
+// there is only one player for now, each one gets a file in the players directory
+const PLAYER_NAME: &'static str = "player";
+
+// items that can be held, chosen with the number keys
+const HOTBAR: [Item; 10] = [
+    Item::Block(Block::Dirt), Item::Block(Block::Glass), Item::Block(Block::Log),
+    Item::Block(Block::Furnace), Item::Block(Block::Slab), Item::Block(Block::Stairs),
+    Item::Block(Block::Fence), Item::Tool(Tool::Pickaxe), Item::Tool(Tool::Axe), Item::Tool(Tool::Shovel),
+];
 
 pub struct GameState {
     chunks: Chunks,
+    entities: Entities,
+    player: Player,
+    recipes: Vec<Recipe>,
+    // ticks since the world was created
+    ticks: u64,
     selected_block: Option<(ChunkPos, BlockPos, Face)>,
+    held: Item,
+    // attack is held down
+    attacking: bool,
+    // the block being broken and how far along that is, in 0..1
+    mining: Option<(ChunkPos, BlockPos, f32)>,
+    // where the player appears after dying, world coordinates of the feet
+    spawn: Point3<f32>,
+    // chunks within this distance of the player stay loaded
+    view_dist: u8,
+    // shared, so the plugins stay reachable while one of them runs a hook
+    plugins: Vec<Rc<RefCell<Box<Plugin>>>>,
+    events: EventBus,
+    // what gets saved to world.txt, the time and spawn are copied in when saving
+    info: WorldInfo,
+    world_dir: PathBuf,
+    // problems that didn't stop the game, until take_errors
+    errors: Vec<String>,
 }
 
 impl GameState {
-    pub fn new() -> GameState {
-        let mut game = GameState {
-            chunks: Chunks::new(),
+    // opens the world in config.world_dir or creates it, the seed and generator settings
+    // of the config only matter for new worlds
+    pub fn new(config: &Config) -> Result<GameState, WorldError> {
+        let world_dir = PathBuf::from(&config.world_dir);
+        let mut info = try!(WorldInfo::load(&world_dir, config));
+        let mut generator = Generator::new(info.seed, info.caves.clone());
+        generator.set_mob_spawning(info.rules.mob_spawning);
+        let spawn = info.spawn.unwrap_or_else(|| Point3::new(0.5, (generator.height_at(0, 0) + 1) as f32, 0.5));
+        info.spawn = Some(spawn);
+        // new and upgraded worlds are written right away, so they keep their seed
+        try!(info.save(&world_dir).map_err(WorldError::WriteError));
+        let (recipes, recipes_error) = crafting::load(Path::new(&config.recipes_path));
+        let mut player = Player::new(spawn);
+        try!(player.load(&GameState::player_path(&world_dir)));
+        Ok(GameState {
+            chunks: Chunks::new(
+                Storage::new(world_dir.clone()),
+                generator,
+                config.unload_radius,
+                config.chunk_budget * 1024 * 1024,
+            ),
+            entities: Entities::new(),
+            player: player,
+            recipes: recipes,
+            ticks: info.time,
             selected_block: None,
-        };
-        for x in -10..10 {
-            for z in -10..10 {
-                game.chunks[Point3::new(x, 0, z)] = Chunk::new_with(Block::Dirt);
+            held: HOTBAR[0],
+            attacking: false,
+            mining: None,
+            spawn: spawn,
+            view_dist: MIN_VIEW_DIST,
+            plugins: Vec::new(),
+            events: EventBus::new(),
+            info: info,
+            world_dir: world_dir,
+            errors: recipes_error.into_iter().collect(),
+        })
+    }
+
+    pub fn register_plugin(&mut self, plugin: Box<Plugin>) {
+        self.plugins.push(Rc::new(RefCell::new(plugin)));
+    }
+
+    // tells the plugins that the world and the player are there, call once after registering them
+    pub fn start(&mut self) {
+        self.run_plugins(|plugin, game| {
+            plugin.world_load(game);
+            true
+        });
+        self.run_plugins(|plugin, game| {
+            plugin.player_join(game);
+            true
+        });
+    }
+
+    // calls hook for every plugin in order until one returns false, which cancels the action.
+    // Actions a hook causes run through the other plugins right away, only the plugin
+    // whose hook is running doesn't hear about them. Plugins registered by a hook come last
+    fn run_plugins<F>(&mut self, mut hook: F) -> bool where F: FnMut(&mut Plugin, &mut GameState) -> bool {
+        let plugins = self.plugins.clone();
+        for plugin in plugins.iter() {
+            let mut plugin = match plugin.try_borrow_mut() {
+                Ok(plugin) => plugin,
+                Err(_) => continue,
+            };
+            if !hook(&mut **plugin, self) {
+                return false;
+            }
+        }
+        true
+    }
+
+    // the commands of all plugins, but the one running a hook right now
+    pub fn plugin_commands(&self) -> Vec<&'static str> {
+        self.plugins.iter()
+            .flat_map(|plugin| plugin.try_borrow().map(|plugin| plugin.commands()).unwrap_or(Vec::new()))
+            .collect()
+    }
+
+    // runs a plugin command, None if no plugin has one with that name
+    pub fn plugin_command(&mut self, name: &str, args: &[&str]) -> Option<Result<String, String>> {
+        let mut result = None;
+        self.run_plugins(|plugin, game| {
+            if plugin.commands().iter().any(|&command| command == name) {
+                result = Some(plugin.command(game, name, args));
+                false
+            } else {
+                true
             }
+        });
+        result
+    }
+
+    // keeps the chunks within radius of the players loaded
+    pub fn update_chunks(&mut self, players: &[ChunkPos], radius: u8) {
+        self.chunks.update(players, radius, &mut self.entities);
+        for entity in self.chunks.take_entities() {
+            self.entities.spawn(entity);
+        }
+        self.collect_events();
+    }
+
+    // advances the simulation by one entity::TICK
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        let tick = self.ticks;
+        self.run_plugins(|plugin, game| {
+            plugin.tick(game, tick);
+            true
+        });
+        self.entities.tick(&self.chunks, self.ticks);
+        self.update_player();
+        self.pick_up();
+        self.mine();
+        self.flush_events();
+        if self.ticks % SAVE_TICKS == 0 {
+            self.save_player();
+        }
+    }
+
+    // events not tied to a tick, like placing a block, get collected until the end of the next one
+    pub fn publish(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    // what couldn't be loaded or saved since the last call, for the player to see
+    pub fn take_errors(&mut self) -> Vec<String> {
+        let mut errors = mem::replace(&mut self.errors, Vec::new());
+        errors.extend(self.chunks.take_errors());
+        errors
+    }
+
+    // everything that happened in the ticks since the last call, in order,
+    // starting with GameEvent::Resync if too much happened to keep it all
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        self.events.take()
+    }
+
+    // moves what chunks and entities recorded onto the bus, this runs after every change
+    // to the loaded chunks, so the events stay in the order things happened in
+    fn collect_events(&mut self) {
+        for event in self.chunks.take_events() {
+            self.events.push(event);
+        }
+        for event in self.entities.take_events() {
+            self.events.push(event);
+        }
+    }
+
+    // hands the events of this tick to the plugins
+    fn flush_events(&mut self) {
+        self.collect_events();
+        for event in self.events.flush() {
+            self.run_plugins(|plugin, game| {
+                plugin.event(game, &event);
+                true
+            });
+        }
+    }
+
+    // advances the mining progress on the target, which starts over when the target changes
+    fn mine(&mut self) {
+        let (c, b) = match (self.attacking && self.player.mode.can_interact(), self.selected_block) {
+            (true, Some((c, b, _))) => (c, b),
+            _ => {
+                self.mining = None;
+                return;
+            },
+        };
+        let progress = match self.mining {
+            Some((mc, mb, progress)) if mc == c && mb == b => progress,
+            _ => 0.0,
+        };
+        // in survival only tools from the inventory help
+        let tool = match self.held {
+            Item::Tool(tool) if self.player.mode != GameMode::Survival
+                             || self.player.inventory.count(self.held) > 0 => Some(tool),
+            _ => None,
+        };
+        let progress = match self.chunks[c][b].break_time(tool) {
+            Some(_) if self.player.mode == GameMode::Creative => 1.0,
+            Some(time) if time > 0.0 => progress + entity::TICK / time,
+            Some(_) => 1.0,
+            None => {
+                self.mining = None;
+                return;
+            },
+        };
+        if progress >= 1.0 {
+            self.mining = None;
+            self.break_block(c, b);
+        } else {
+            self.mining = Some((c, b, progress));
+        }
+    }
+
+    fn update_player(&mut self) {
+        self.player.tick(&self.chunks, self.ticks, &self.info.rules);
+        if self.player.is_dead() {
+            self.player.respawn(self.spawn, self.info.rules.keep_inventory);
+        }
+    }
+
+    // moves items close to the player into the inventory, as far as they fit
+    fn pick_up(&mut self) {
+        if !self.player.mode.can_interact() {
+            return;
+        }
+        let region = self.player.bbox().grow(PICKUP_RADIUS);
+        for id in self.entities.in_region(&region) {
+            let (item, count) = match self.entities.get(id).map(|e| e.kind) {
+                Some(Kind::Item { item, count, age }) if age >= entity::PICKUP_DELAY => (item, count),
+                _ => continue,
+            };
+            let left = self.player.inventory.add(item, count);
+            self.entities.set_item_count(id, left);
+        }
+    }
+
+    pub fn set_player_eye(&mut self, eye: Point3<f32>) {
+        self.player.set_eye(eye);
+    }
+
+    // the direction the player wants to walk in, applied on the next tick
+    pub fn set_player_input(&mut self, input: Vector3<f32>) {
+        self.player.input = input;
+    }
+
+    // where the player looks, in degrees, only kept to be saved
+    pub fn set_player_look(&mut self, phi: f32, theta: f32) {
+        self.player.phi = phi;
+        self.player.theta = theta;
+    }
+
+    // moves the feet of the player to a world position
+    pub fn set_player_pos(&mut self, pos: Point3<f32>) {
+        self.player.pos = pos;
+        self.player.velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    pub fn set_game_mode(&mut self, mode: GameMode) {
+        self.player.set_mode(mode);
+        if !mode.can_interact() {
+            self.selected_block = None;
+            self.mining = None;
+        }
+    }
+
+    pub fn get_spawn(&self) -> Point3<f32> {
+        self.spawn
+    }
+
+    pub fn rules(&self) -> &GameRules {
+        &self.info.rules
+    }
+
+    pub fn get_game_mode(&self) -> GameMode {
+        self.player.mode
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn set_ticks(&mut self, ticks: u64) {
+        self.ticks = ticks;
+    }
+
+    pub fn get_view_dist(&self) -> u8 {
+        self.view_dist
+    }
+
+    pub fn set_view_dist(&mut self, view_dist: u8) {
+        self.view_dist = ::std::cmp::min(MAX_VIEW_DIST, ::std::cmp::max(MIN_VIEW_DIST, view_dist));
+    }
+
+    pub fn save(&mut self) {
+        self.chunks.save_all(&self.entities);
+        self.info.time = self.ticks;
+        self.info.spawn = Some(self.spawn);
+        if let Err(e) = self.info.save(&self.world_dir) {
+            self.errors.push(format!("Could not save the world info: {}", e));
+        }
+        self.save_player();
+    }
+
+    fn player_path(world_dir: &Path) -> PathBuf {
+        world_dir.join("players").join(format!("{}.txt", PLAYER_NAME))
+    }
+
+    pub fn save_player(&mut self) {
+        if let Err(e) = self.player.save(&GameState::player_path(&self.world_dir)) {
+            self.errors.push(format!("Could not save the player: {}", e));
         }
-        //game.chunks[Point3::new(0,0,0)][Point3::new(0,0,0)] = Block::Dirt;
-        game
     }
 
     fn normalize(mut chunk: ChunkPos, mut block: Point3<i8>) -> (ChunkPos, BlockPos) {
@@ -40,31 +385,580 @@ impl GameState {
         (chunk, Point3::from_vec(block.to_vec().cast()))
     }
 
+    // spectators can't target anything
     pub fn set_selected_block(&mut self, block: Option<(ChunkPos, BlockPos, Face)>) {
-        self.selected_block = block;
+        self.selected_block = if self.player.mode.can_interact() { block } else { None };
     }
 
     pub fn get_selected_block(&self) -> Option<(ChunkPos, BlockPos, Face)> {
         self.selected_block
     }
 
-    pub fn attack(&mut self) {
-        self.selected_block.map(|(c, b, _)| self.chunks[c][b] = Block::Air);
+    pub fn select(&mut self, slot: u8) {
+        if let Some(&item) = HOTBAR.get(slot as usize) {
+            self.held = item;
+        }
+    }
+
+    pub fn get_held(&self) -> Item {
+        self.held
     }
 
-    pub fn place(&mut self) {
-        self.selected_block.map(|(c, b, f)| {
-            let b = Point3::from_vec((
-                  b.to_vec().cast()
-                + f.to_vec()
-            ));
+    // while attacking the target gets mined, see mine
+    pub fn set_attacking(&mut self, attacking: bool) {
+        self.attacking = attacking;
+    }
 
-            let (c, b) = GameState::normalize(c, b);
-            self.chunks[c][b] = Block::Dirt
-        });
+    // the block being broken and the progress in 0..1
+    pub fn get_mining(&self) -> Option<(ChunkPos, BlockPos, f32)> {
+        self.mining
+    }
+
+    // what the block drops flies off in a random direction, creative drops nothing
+    fn break_block(&mut self, c: ChunkPos, b: BlockPos) {
+        let block = self.chunks[c][b];
+        let (x, y, z) = (c.x * 16 + b.x as i32, c.y * 16 + b.y as i32, c.z * 16 + b.z as i32);
+        let mut drops = if self.player.mode == GameMode::Creative { Vec::new() } else { block.drops() };
+        if !self.run_plugins(|plugin, game| plugin.block_break(game, Point3::new(x, y, z), block, &mut drops)) {
+            return;
+        }
+        self.change_block(c, b, Block::Air, BlockState::new());
+        for (i, (drop, count)) in drops.into_iter().enumerate() {
+            let h = noise::hash(self.ticks ^ i as u64, x, y, z);
+            let velocity = Vector3::new(
+                noise::hash_unit(h, 0, 0, 0) * 2.0 - 1.0,
+                3.0,
+                noise::hash_unit(h, 1, 0, 0) * 2.0 - 1.0,
+            );
+            let item = Kind::Item { item: drop, count: count, age: 0 };
+            let pos = Point3::new(x as f32 + 0.5, y as f32 + 0.375, z as f32 + 0.5);
+            self.entities.spawn(Entity::new(item, pos, velocity));
+        }
+    }
+
+    // facing is the horizontal direction the player looks towards,
+    // survival takes the block from the inventory while creative has unlimited blocks
+    pub fn place(&mut self, facing: Face) {
+        let held = match self.held {
+            Item::Block(block) => block,
+            Item::Tool(_)      => return,
+        };
+        let (c, b, f) = match self.selected_block {
+            Some(selected) => selected,
+            None => return,
+        };
+        if !self.player.mode.can_interact() {
+            return;
+        }
+        let b = Point3::from_vec((
+              b.to_vec().cast()
+            + f.to_vec()
+        ));
+        let (c, b) = GameState::normalize(c, b);
+
+        // plugins only hear about placements that can happen
+        let survival = self.player.mode == GameMode::Survival;
+        if survival && self.player.inventory.count(self.held) == 0 {
+            return;
+        }
+        let pos = Point3::new(c.x * 16 + b.x as i32, c.y * 16 + b.y as i32, c.z * 16 + b.z as i32);
+        let mut block = held;
+        let mut state = held.placed_state(f, facing);
+        if !self.run_plugins(|plugin, game| plugin.block_place(game, pos, &mut block, &mut state)) {
+            return;
+        }
+        if survival && !self.player.inventory.remove(self.held, 1) {
+            return;
+        }
+        self.change_block(c, b, block, state);
+    }
+
+    // replaces the block at a world position, loading its chunk if needed. The plugins hear
+    // about it like about breaking without drops or placing, false if one of them cancelled it
+    pub fn set_block(&mut self, pos: Point3<i32>, block: Block) -> bool {
+        let (c, b) = entity::split(pos);
+        let old = self.chunks[c][b];
+        let mut block = block;
+        let mut state = BlockState::new();
+        let allowed = if block == Block::Air {
+            self.run_plugins(|plugin, game| plugin.block_break(game, pos, old, &mut Vec::new()))
+        } else {
+            self.run_plugins(|plugin, game| plugin.block_place(game, pos, &mut block, &mut state))
+        };
+        if allowed {
+            self.change_block(c, b, block, state);
+        }
+        allowed
+    }
+
+    // every gameplay change of a block goes through here, so it gets published
+    fn change_block(&mut self, c: ChunkPos, b: BlockPos, block: Block, state: BlockState) {
+        let (old, changed) = {
+            let chunk = &mut self.chunks[c];
+            let (old, version) = (chunk[b], chunk.version());
+            chunk.set(b, block);
+            chunk.set_state(b, state);
+            (old, chunk.version() != version)
+        };
+        // the chunk may just have been loaded
+        self.collect_events();
+        if !changed {
+            return;
+        }
+        let pos = Point3::new(c.x * 16 + b.x as i32, c.y * 16 + b.y as i32, c.z * 16 + b.z as i32);
+        self.events.push(GameEvent::BlockChanged { pos: pos, old: old, new: block });
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    // crafts what the grid makes out of items from the inventory,
+    // false if nothing matches or the inventory lacks the ingredients
+    pub fn craft(&mut self, grid: &Grid) -> bool {
+        if !self.player.mode.can_interact() {
+            return false;
+        }
+        let (output, count) = match crafting::find(&self.recipes, grid) {
+            Some(recipe) => (recipe.output, recipe.count),
+            None => return false,
+        };
+        let items = grid.items();
+        let inventory = &mut self.player.inventory;
+        let mut needed: Vec<(Item, u8)> = Vec::new();
+        for item in items {
+            match needed.iter().position(|&(i, _)| i == item) {
+                Some(n) => needed[n].1 += 1,
+                None => needed.push((item, 1)),
+            }
+        }
+        if needed.iter().any(|&(item, n)| inventory.count(item) < n as u32) {
+            return false;
+        }
+        for &(item, n) in &needed {
+            inventory.remove(item, n);
+        }
+        // what doesn't fit gets dropped at the feet of the player
+        let left = inventory.add(output, count);
+        if left > 0 {
+            let item = Kind::Item { item: output, count: left, age: 0 };
+            self.entities.spawn(Entity::new(item, self.player.pos, Vector3::new(0.0, 0.0, 0.0)));
+        }
+        true
+    }
+
+    // throws a projectile from a world position, dir has to be normalized
+    pub fn throw(&mut self, from: Point3<f32>, dir: Vector3<f32>) -> Option<EntityId> {
+        if !self.player.mode.can_interact() {
+            return None;
+        }
+        let projectile = Entity::new(Kind::Projectile { age: 0 }, from, dir * THROW_SPEED);
+        Some(self.entities.spawn(projectile))
+    }
+
+    pub fn spawn(&mut self, entity: Entity) -> EntityId {
+        self.entities.spawn(entity)
+    }
+
+    pub fn entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities.get(id)
+    }
+
+    pub fn entity_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities.get_mut(id)
+    }
+
+    // ids of the entities touching a region in world coordinates
+    pub fn entities_in(&self, region: &Aabb) -> Vec<EntityId> {
+        self.entities.in_region(region)
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
     }
 
     pub fn chunk(&self, pos: ChunkPos) -> Chunk {
         self.chunks[pos]
     }
+
+    pub fn loaded_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_loaded(&self, pos: ChunkPos) -> bool {
+        self.chunks.is_loaded(pos)
+    }
+
+    // bytes used by all loaded chunks
+    pub fn chunk_memory(&self) -> usize {
+        self.chunks.memory()
+    }
+
+    pub fn chunk_stats(&self) -> ChunkStats {
+        self.chunks.get_stats()
+    }
+
+    pub fn biome(&self, chunk: ChunkPos, x: u8, z: u8) -> Biome {
+        self.chunks[chunk].biome(x, z)
+    }
+
+    pub fn block(&self, chunk: ChunkPos, block: BlockPos) -> Block {
+        self.chunks[chunk][block]
+    }
+
+    pub fn state(&self, chunk: ChunkPos, block: BlockPos) -> BlockState {
+        self.chunks[chunk].state(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::mem;
+    use std::rc::Rc;
+
+    use cgmath::{ Point3, Vector3 };
+
+    use config::Config;
+    use types::Face;
+    use logic::block::{ Block, BlockState };
+    use logic::command::Commands;
+    use logic::chunk::BlockPos;
+    use logic::chunks::ChunkPos;
+    use logic::entity::{ self, TICK };
+    use logic::item::{ Item, Tool };
+    use logic::player::GameMode;
+    use logic::plugin::Plugin;
+    use logic::testing::TempDir;
+    use super::*;
+
+    // a fresh world in the directory of the test
+    fn game(dir: &TempDir) -> GameState {
+        let mut config = Config::new();
+        config.world_dir = dir.path().to_string_lossy().into_owned();
+        GameState::new(&config).unwrap()
+    }
+
+    // a block high up in the air, so nothing else is around
+    const TARGET: (i32, i32, i32) = (3, 100, 3);
+
+    fn target(game: &mut GameState, block: Block) -> (ChunkPos, BlockPos) {
+        let pos = Point3::new(TARGET.0, TARGET.1, TARGET.2);
+        let (c, b) = entity::split(pos);
+        game.change_block(c, b, block, BlockState::new());
+        game.selected_block = Some((c, b, Face::Top));
+        (c, b)
+    }
+
+    fn mining_progress(game: &GameState) -> f32 {
+        game.mining.map(|(_, _, progress)| progress).unwrap_or(0.0)
+    }
+
+    // writes every hook it sees into the log shared by all recorders
+    struct Recorder {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+        // what the bool hooks return
+        allow: bool,
+        // replaces the drops of broken blocks
+        drops: Option<Vec<(Item, u8)>>,
+    }
+
+    impl Recorder {
+        fn record(&self, hook: String) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, hook));
+        }
+    }
+
+    impl Plugin for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn world_load(&mut self, _: &mut GameState) {
+            self.record("world_load".to_string());
+        }
+
+        fn player_join(&mut self, _: &mut GameState) {
+            self.record("player_join".to_string());
+        }
+
+        fn tick(&mut self, _: &mut GameState, tick: u64) {
+            self.record(format!("tick {}", tick));
+        }
+
+        fn block_break(&mut self, _: &mut GameState, _: Point3<i32>, block: Block, drops: &mut Vec<(Item, u8)>) -> bool {
+            self.record(format!("break {:?} {:?}", block, drops));
+            if let Some(ref replaced) = self.drops {
+                *drops = replaced.clone();
+            }
+            self.allow
+        }
+
+        fn block_place(&mut self, _: &mut GameState, _: Point3<i32>, block: &mut Block, _: &mut BlockState) -> bool {
+            self.record(format!("place {:?}", block));
+            self.allow
+        }
+    }
+
+    // a game with a recorder named a and one named b, in that order
+    fn recorded(dir: &TempDir, allow_a: bool, drops_a: Option<Vec<(Item, u8)>>) -> (GameState, Rc<RefCell<Vec<String>>>) {
+        let mut game = game(dir);
+        let log = Rc::new(RefCell::new(Vec::new()));
+        game.register_plugin(Box::new(Recorder { name: "a", log: log.clone(), allow: allow_a, drops: drops_a }));
+        game.register_plugin(Box::new(Recorder { name: "b", log: log.clone(), allow: true, drops: None }));
+        (game, log)
+    }
+
+    fn take_log(log: &Rc<RefCell<Vec<String>>>) -> Vec<String> {
+        mem::replace(&mut *log.borrow_mut(), Vec::new())
+    }
+
+    // puts a stone on top of every block placed and remembers if it was allowed to
+    struct Builder {
+        stacked: Rc<RefCell<Vec<bool>>>,
+    }
+
+    impl Plugin for Builder {
+        fn name(&self) -> &str {
+            "builder"
+        }
+
+        fn block_place(&mut self, game: &mut GameState, pos: Point3<i32>, _: &mut Block, _: &mut BlockState) -> bool {
+            let allowed = game.set_block(pos + Vector3::new(0, 1, 0), Block::Stone);
+            self.stacked.borrow_mut().push(allowed);
+            true
+        }
+    }
+
+    // nothing may be placed at one position
+    struct Guard {
+        protected: Point3<i32>,
+    }
+
+    impl Plugin for Guard {
+        fn name(&self) -> &str {
+            "guard"
+        }
+
+        fn block_place(&mut self, _: &mut GameState, pos: Point3<i32>, _: &mut Block, _: &mut BlockState) -> bool {
+            pos != self.protected
+        }
+    }
+
+    #[test]
+    fn hooks_run_in_registration_order() {
+        let dir = TempDir::new("plugin-order");
+        let (mut game, log) = recorded(&dir, true, None);
+        game.start();
+        game.tick();
+        game.tick();
+        assert_eq!(take_log(&log), vec![
+            "a world_load", "b world_load",
+            "a player_join", "b player_join",
+            "a tick 1", "b tick 1",
+            "a tick 2", "b tick 2",
+        ]);
+    }
+
+    #[test]
+    fn a_cancelled_break_skips_later_plugins() {
+        let dir = TempDir::new("plugin-break-cancel");
+        let (mut game, log) = recorded(&dir, false, None);
+        let (c, b) = target(&mut game, Block::Stone);
+        game.break_block(c, b);
+        assert_eq!(take_log(&log), vec!["a break Stone [(Block(Stone), 1)]"]);
+        assert_eq!(game.chunks[c][b], Block::Stone);
+        assert_eq!(game.entities.len(), 0);
+    }
+
+    #[test]
+    fn plugins_change_the_drops() {
+        let sand = vec![(Item::Block(Block::Sand), 3)];
+        let dir = TempDir::new("plugin-drops");
+        let (mut game, log) = recorded(&dir, true, Some(sand));
+        let (c, b) = target(&mut game, Block::Stone);
+        game.break_block(c, b);
+        assert_eq!(take_log(&log), vec![
+            "a break Stone [(Block(Stone), 1)]",
+            "b break Stone [(Block(Sand), 3)]",
+        ]);
+        assert_eq!(game.chunks[c][b], Block::Air);
+        let items: Vec<(Item, u8)> = game.entities.iter().filter_map(|(&id, _)| game.entities.item(id)).collect();
+        assert_eq!(items, vec![(Item::Block(Block::Sand), 3)]);
+    }
+
+    #[test]
+    fn a_cancelled_place_keeps_the_block_in_the_inventory() {
+        let dir = TempDir::new("plugin-place-cancel");
+        let (mut game, log) = recorded(&dir, false, None);
+        let (c, b) = target(&mut game, Block::Stone);
+        game.held = Item::Block(Block::Dirt);
+        game.player.inventory.add(Item::Block(Block::Dirt), 1);
+        game.place(Face::North);
+        assert_eq!(take_log(&log), vec!["a place Dirt"]);
+        assert_eq!(game.chunks[c][b + Vector3::new(0, 1, 0)], Block::Air);
+        assert_eq!(game.player.inventory.count(Item::Block(Block::Dirt)), 1);
+    }
+
+    #[test]
+    fn placing_takes_the_block_after_the_plugins_allowed_it() {
+        let dir = TempDir::new("plugin-place");
+        let (mut game, log) = recorded(&dir, true, None);
+        let (c, b) = target(&mut game, Block::Stone);
+        game.held = Item::Block(Block::Dirt);
+
+        // nothing to place, so the plugins don't hear about it
+        game.place(Face::North);
+        assert!(take_log(&log).is_empty());
+        assert_eq!(game.chunks[c][b + Vector3::new(0, 1, 0)], Block::Air);
+
+        game.player.inventory.add(Item::Block(Block::Dirt), 2);
+        game.place(Face::North);
+        assert_eq!(take_log(&log), vec!["a place Dirt", "b place Dirt"]);
+        assert_eq!(game.chunks[c][b + Vector3::new(0, 1, 0)], Block::Dirt);
+        assert_eq!(game.player.inventory.count(Item::Block(Block::Dirt)), 1);
+    }
+
+    #[test]
+    fn survival_players_only_mine_with_tools_they_have() {
+        let dir = TempDir::new("tools");
+        let mut game = game(&dir);
+        target(&mut game, Block::Stone);
+        game.attacking = true;
+        game.held = Item::Tool(Tool::Pickaxe);
+        let by_hand = TICK / Block::Stone.hardness().unwrap();
+
+        game.mine();
+        assert!((mining_progress(&game) - by_hand).abs() < 1e-6);
+
+        game.mining = None;
+        game.player.inventory.add(Item::Tool(Tool::Pickaxe), 1);
+        game.mine();
+        assert!((mining_progress(&game) - by_hand * Tool::Pickaxe.speed()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn the_view_distance_stays_in_range() {
+        let dir = TempDir::new("view-dist");
+        let mut game = game(&dir);
+        game.set_view_dist(0);
+        assert_eq!(game.get_view_dist(), MIN_VIEW_DIST);
+        game.set_view_dist(255);
+        assert_eq!(game.get_view_dist(), MAX_VIEW_DIST);
+        game.set_view_dist(10);
+        assert_eq!(game.get_view_dist(), 10);
+
+        let commands = Commands::defaults();
+        assert!(commands.execute(&mut game, "/viewdist 255").is_err());
+        assert!(commands.execute(&mut game, "/viewdist 13").is_err());
+        assert_eq!(game.get_view_dist(), 10);
+        assert!(commands.execute(&mut game, "/viewdist 12").is_ok());
+        assert_eq!(game.get_view_dist(), 12);
+        assert!(Chunks::around(MAX_VIEW_DIST, Point3::new(0, 0, 0)).len() < 20000);
+    }
+
+    #[test]
+    fn creative_players_mine_with_any_tool() {
+        let dir = TempDir::new("creative-tools");
+        let mut game = game(&dir);
+        let (c, b) = target(&mut game, Block::Stone);
+        game.set_game_mode(GameMode::Creative);
+        game.selected_block = Some((c, b, Face::Top));
+        game.attacking = true;
+        game.held = Item::Tool(Tool::Pickaxe);
+        game.mine();
+        assert_eq!(game.chunks[c][b], Block::Air);
+    }
+
+    #[test]
+    fn a_chunk_unloaded_and_loaded_again_ends_up_loaded() {
+        let dir = TempDir::new("reload");
+        let mut game = game(&dir);
+        let here = Point3::new(0, 6, 0);
+        let far = Point3::new(20, 6, 0);
+        game.update_chunks(&[here], 1);
+        let item = Kind::Item { item: Item::Block(Block::Sand), count: 1, age: 0 };
+        let id = game.spawn(Entity::new(item, Point3::new(8.0, 104.0, 8.0), Vector3::new(0.0, 0.0, 0.0)));
+        game.tick();
+        game.take_events();
+
+        game.update_chunks(&[far], 1);
+        game.update_chunks(&[here], 1);
+        game.tick();
+        let events: Vec<GameEvent> = game.take_events().into_iter().filter(|event| match *event {
+            GameEvent::ChunkLoaded(pos) | GameEvent::ChunkUnloaded(pos) => pos == here,
+            GameEvent::EntityUnloaded(_) => true,
+            _ => false,
+        }).collect();
+        assert_eq!(events, vec![
+            GameEvent::EntityUnloaded(id),
+            GameEvent::ChunkUnloaded(here),
+            GameEvent::ChunkLoaded(here),
+        ]);
+        assert!(game.chunks.is_loaded(here));
+        // the item came back with its chunk
+        assert_eq!(game.entities().len(), 1);
+        assert!(game.entities().get(id).is_none());
+    }
+
+    #[test]
+    fn other_plugins_can_cancel_what_a_hook_does() {
+        let dir = TempDir::new("plugin-nested");
+        let mut game = game(&dir);
+        let pos = Point3::new(TARGET.0, TARGET.1, TARGET.2);
+        let above = pos + Vector3::new(0, 1, 0);
+        let stacked = Rc::new(RefCell::new(Vec::new()));
+        game.register_plugin(Box::new(Builder { stacked: stacked.clone() }));
+        game.register_plugin(Box::new(Guard { protected: above }));
+
+        assert!(game.set_block(pos, Block::Dirt));
+        assert_eq!(*stacked.borrow(), vec![false]);
+        let (c, b) = entity::split(pos);
+        assert_eq!(game.chunks[c][b], Block::Dirt);
+        let (c, b) = entity::split(above);
+        assert_eq!(game.chunks[c][b], Block::Air);
+
+        // the builder doesn't hear about its own stone, the guard allows it elsewhere
+        let beside = pos + Vector3::new(1, 0, 0);
+        assert!(game.set_block(beside, Block::Dirt));
+        assert_eq!(*stacked.borrow(), vec![false, true]);
+        let (c, b) = entity::split(beside + Vector3::new(0, 1, 0));
+        assert_eq!(game.chunks[c][b], Block::Stone);
+    }
+
+    #[test]
+    fn setblock_asks_the_plugins_and_the_game_mode() {
+        let dir = TempDir::new("setblock");
+        let (mut game, log) = recorded(&dir, true, None);
+        let commands = Commands::defaults();
+        let (c, b) = entity::split(Point3::new(TARGET.0, TARGET.1, TARGET.2));
+        let line = format!("/setblock {} {} {} stone", TARGET.0, TARGET.1, TARGET.2);
+
+        assert!(commands.execute(&mut game, &line).is_ok());
+        assert_eq!(take_log(&log), vec!["a place Stone", "b place Stone"]);
+        assert_eq!(game.chunks[c][b], Block::Stone);
+
+        let air = format!("/setblock {} {} {} air", TARGET.0, TARGET.1, TARGET.2);
+        game.set_game_mode(GameMode::Spectator);
+        assert!(commands.execute(&mut game, &air).is_err());
+        assert!(take_log(&log).is_empty());
+        assert_eq!(game.chunks[c][b], Block::Stone);
+
+        game.set_game_mode(GameMode::Creative);
+        assert!(commands.execute(&mut game, &air).is_ok());
+        assert_eq!(take_log(&log), vec!["a break Stone []", "b break Stone []"]);
+        assert_eq!(game.chunks[c][b], Block::Air);
+    }
+
+    #[test]
+    fn setblock_fails_when_a_plugin_cancels() {
+        let dir = TempDir::new("setblock-cancel");
+        let (mut game, log) = recorded(&dir, false, None);
+        let line = format!("/setblock {} {} {} stone", TARGET.0, TARGET.1, TARGET.2);
+        assert!(Commands::defaults().execute(&mut game, &line).is_err());
+        assert_eq!(take_log(&log), vec!["a place Stone"]);
+        let (c, b) = entity::split(Point3::new(TARGET.0, TARGET.1, TARGET.2));
+        assert_eq!(game.chunks[c][b], Block::Air);
+    }
 }
@@ -0,0 +1,190 @@
+use cgmath::{ Point3, Vector3 };
+
+use super::biome::Biome;
+use super::carver::{ Carver, CarverSettings };
+use super::block::Block;
+use super::chunk::Chunk;
+use super::chunks::ChunkPos;
+use super::entity::{ Entity, Kind };
+use super::feature::{ self, Feature };
+use super::noise::{ self, Noise };
+
+// water fills everything up to this height
+pub const SEA_LEVEL: i32 = 8;
+// chance of a chunk with grass to spawn a mob when it is generated
+const MOB_CHANCE: f32 = 0.1;
+// blocks of filler between the surface and the stone
+const FILLER_DEPTH: i32 = 3;
+// size of climate zones and hills in blocks
+const CLIMATE_SCALE: f32 = 256.0;
+const HILL_SCALE: f32 = 48.0;
+// biomes within this many blocks of a column contribute to its height
+const BLEND_RADIUS: i32 = 8;
+const BLEND_STEP: i32 = 4;
+
+// creates chunks that were never saved, the same seed always gives the same world
+#[derive(Debug)]
+pub struct Generator {
+    seed: u64,
+    temperature: Noise,
+    humidity: Noise,
+    hills: Noise,
+    carver: Carver,
+    // the mob_spawning game rule
+    mobs: bool,
+}
+
+impl Generator {
+    pub fn new(seed: u64, caves: CarverSettings) -> Generator {
+        let noise = Noise::new(seed);
+        Generator {
+            seed: seed,
+            temperature: noise.derive(1),
+            humidity: noise.derive(2),
+            hills: noise.derive(3),
+            carver: Carver::new(seed, caves),
+            mobs: true,
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_mob_spawning(&mut self, mobs: bool) {
+        self.mobs = mobs;
+    }
+
+    // temperature and humidity in 0..1
+    fn climate(&self, x: i32, z: i32) -> (f32, f32) {
+        let (x, z) = (x as f32 / CLIMATE_SCALE, z as f32 / CLIMATE_SCALE);
+        (
+            self.temperature.fractal2(x, z, 3) * 0.5 + 0.5,
+            self.humidity.fractal2(x, z, 3) * 0.5 + 0.5,
+        )
+    }
+
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        let (temperature, humidity) = self.climate(x, z);
+        Biome::from_climate(temperature, humidity)
+    }
+
+    // y of the topmost solid block, the base height and height scale are averaged over
+    // the biomes around the column so there are no cliffs at biome borders
+    pub fn height_at(&self, x: i32, z: i32) -> i32 {
+        let (mut base, mut scale, mut samples) = (0.0, 0.0, 0.0);
+        let mut dx = -BLEND_RADIUS;
+        while dx <= BLEND_RADIUS {
+            let mut dz = -BLEND_RADIUS;
+            while dz <= BLEND_RADIUS {
+                let biome = self.biome_at(x + dx, z + dz);
+                base += biome.base_height();
+                scale += biome.height_scale();
+                samples += 1.0;
+                dz += BLEND_STEP;
+            }
+            dx += BLEND_STEP;
+        }
+        let hills = self.hills.fractal2(x as f32 / HILL_SCALE, z as f32 / HILL_SCALE, 4);
+        ((base + scale * hills) / samples).floor() as i32
+    }
+
+    pub fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new();
+        let mut heights = [[0; 16]; 16];
+        for x in 0..16u8 {
+            for z in 0..16u8 {
+                let (wx, wz) = (pos.x * 16 + x as i32, pos.z * 16 + z as i32);
+                let biome = self.biome_at(wx, wz);
+                let height = self.height_at(wx, wz);
+                heights[x as usize][z as usize] = height;
+                chunk.set_biome(x, z, biome);
+                for y in 0..16u8 {
+                    let wy = pos.y * 16 + y as i32;
+                    let block = if wy < height - FILLER_DEPTH {
+                        Block::Stone
+                    } else if wy < height {
+                        biome.filler()
+                    } else if wy == height {
+                        // underwater there is no grass or snow
+                        if height < SEA_LEVEL { biome.filler() } else { biome.surface() }
+                    } else if wy <= SEA_LEVEL {
+                        Block::Water
+                    } else {
+                        Block::Air
+                    };
+                    if block != Block::Air {
+                        chunk.set(Point3::new(x, y, z), block);
+                    }
+                }
+            }
+        }
+        self.carver.carve(&mut chunk, pos, &heights);
+        self.decorate(&mut chunk, pos);
+        chunk
+    }
+
+    // mobs living in a freshly generated chunk, they stand on grass with air above
+    pub fn spawn(&self, pos: ChunkPos, chunk: &Chunk) -> Vec<Entity> {
+        if !self.mobs || noise::hash_unit(self.seed, pos.x, pos.y, pos.z) >= MOB_CHANCE {
+            return Vec::new();
+        }
+        let h = noise::hash(self.seed, pos.x, pos.y + 1, pos.z);
+        let (x, z) = ((h % 16) as u8, (h / 16 % 16) as u8);
+        for y in (0..15u8).rev() {
+            if chunk[Point3::new(x, y, z)] == Block::Grass && chunk[Point3::new(x, y + 1, z)] == Block::Air {
+                let at = Point3::new(
+                    (pos.x * 16 + x as i32) as f32 + 0.5,
+                    (pos.y * 16 + y as i32) as f32 + 1.0,
+                    (pos.z * 16 + z as i32) as f32 + 0.5,
+                );
+                let mob = Kind::Mob { walking: false, heading: 0.0 };
+                return vec![Entity::new(mob, at, Vector3::new(0.0, 0.0, 0.0))];
+            }
+        }
+        Vec::new()
+    }
+
+    // the feature rooted in a column, if any, only depends on the seed and the column
+    pub fn feature_at(&self, x: i32, z: i32) -> Option<(Feature, i32)> {
+        let roll = noise::hash_unit(self.seed, x, 0, z);
+        let biome = self.biome_at(x, z);
+        if roll >= biome.decoration_density() {
+            return None;
+        }
+        let height = self.height_at(x, z);
+        if height < SEA_LEVEL {
+            return None;
+        }
+        let variation = noise::hash(self.seed, x, 1, z);
+        Some((Feature::choose(biome, variation), height + 1))
+    }
+
+    // places the parts of all features that reach into this chunk,
+    // including those rooted in neighbouring chunks, so the generation order doesn't matter
+    fn decorate(&self, chunk: &mut Chunk, pos: ChunkPos) {
+        let (min_x, min_y, min_z) = (pos.x * 16, pos.y * 16, pos.z * 16);
+        // origins are visited in the same order by every chunk, so overlapping features
+        // always resolve the same way
+        for ox in min_x - feature::MAX_REACH..min_x + 16 + feature::MAX_REACH {
+            for oz in min_z - feature::MAX_REACH..min_z + 16 + feature::MAX_REACH {
+                let (feature, oy) = match self.feature_at(ox, oz) {
+                    Some(f) => f,
+                    None => continue,
+                };
+                let variation = noise::hash(self.seed, ox, oy, oz);
+                for (offset, block) in feature.blocks(variation) {
+                    let (x, y, z) = (ox + offset.x - min_x, oy + offset.y - min_y, oz + offset.z - min_z);
+                    if x < 0 || x >= 16 || y < 0 || y >= 16 || z < 0 || z >= 16 {
+                        continue;
+                    }
+                    // features only grow into air
+                    let pos = Point3::new(x as u8, y as u8, z as u8);
+                    if chunk[pos] == Block::Air {
+                        chunk.set(pos, block);
+                    }
+                }
+            }
+        }
+    }
+}
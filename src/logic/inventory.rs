@@ -0,0 +1,133 @@
+use std::cmp;
+
+use super::item::Item;
+
+const SLOTS: usize = 36;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stack {
+    pub item: Item,
+    pub count: u8,
+}
+
+#[derive(Clone, Debug)]
+pub struct Inventory {
+    slots: Vec<Option<Stack>>,
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory {
+            slots: vec![None; SLOTS],
+        }
+    }
+
+    pub fn slots(&self) -> &[Option<Stack>] {
+        &self.slots
+    }
+
+    // false if there is no such slot
+    pub fn set_slot(&mut self, index: usize, stack: Option<Stack>) -> bool {
+        match self.slots.get_mut(index) {
+            Some(slot) => {
+                *slot = stack;
+                true
+            },
+            None => false,
+        }
+    }
+
+    // fills up existing stacks of the item first, then empty slots,
+    // returns how many didn't fit
+    pub fn add(&mut self, item: Item, mut count: u8) -> u8 {
+        let max = item.max_stack();
+        for slot in self.slots.iter_mut() {
+            if let Some(ref mut stack) = *slot {
+                if stack.item == item {
+                    let moved = cmp::min(count, max.saturating_sub(stack.count));
+                    stack.count += moved;
+                    count -= moved;
+                }
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if count == 0 {
+                break;
+            }
+            if slot.is_none() {
+                let moved = cmp::min(count, max);
+                *slot = Some(Stack { item: item, count: moved });
+                count -= moved;
+            }
+        }
+        count
+    }
+
+    // false without taking anything if there are fewer than count
+    pub fn remove(&mut self, item: Item, mut count: u8) -> bool {
+        if self.count(item) < count as u32 {
+            return false;
+        }
+        // the last stacks go first, so the front of the inventory stays filled
+        for slot in self.slots.iter_mut().rev() {
+            if count == 0 {
+                break;
+            }
+            let emptied = match *slot {
+                Some(ref mut stack) if stack.item == item => {
+                    let moved = cmp::min(count, stack.count);
+                    stack.count -= moved;
+                    count -= moved;
+                    stack.count == 0
+                },
+                _ => false,
+            };
+            if emptied {
+                *slot = None;
+            }
+        }
+        true
+    }
+
+    pub fn count(&self, item: Item) -> u32 {
+        self.slots.iter()
+            .filter_map(|slot| *slot)
+            .filter(|stack| stack.item == item)
+            .fold(0, |sum, stack| sum + stack.count as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use logic::block::Block;
+    use logic::item::{ Item, Tool };
+    use super::*;
+
+    #[test]
+    fn stacks_fill_up_before_empty_slots() {
+        let sand = Item::Block(Block::Sand);
+        let mut inventory = Inventory::new();
+        inventory.set_slot(3, Some(Stack { item: sand, count: 60 }));
+        assert_eq!(inventory.add(sand, 10), 0);
+        assert_eq!(inventory.slots()[3], Some(Stack { item: sand, count: 64 }));
+        assert_eq!(inventory.slots()[0], Some(Stack { item: sand, count: 6 }));
+    }
+
+    #[test]
+    fn overfull_stacks_take_nothing() {
+        let axe = Item::Tool(Tool::Axe);
+        let mut inventory = Inventory::new();
+        inventory.set_slot(0, Some(Stack { item: axe, count: 2 }));
+        assert_eq!(inventory.add(axe, 1), 0);
+        assert_eq!(inventory.slots()[0], Some(Stack { item: axe, count: 2 }));
+        assert_eq!(inventory.count(axe), 3);
+    }
+
+    #[test]
+    fn what_doesnt_fit_is_returned() {
+        let axe = Item::Tool(Tool::Axe);
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add(axe, 40), 4);
+        assert_eq!(inventory.count(axe), 36);
+    }
+}
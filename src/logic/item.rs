@@ -0,0 +1,83 @@
+use std::fmt;
+
+use types::Face;
+use super::block::{ Block, BlockState };
+
+// items with an id from here on are tools, the ones below are blocks
+pub const FIRST_TOOL_ID: u8 = 128;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tool {
+    Pickaxe,
+    Axe,
+    Shovel,
+}
+
+impl Tool {
+    // breaking the blocks a tool is made for is this many times faster
+    pub fn speed(self) -> f32 {
+        4.0
+    }
+}
+
+// anything that can lie around or be kept in the inventory
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Item {
+    Block(Block),
+    Tool(Tool),
+}
+
+impl Item {
+    // stable id used when saving, never reorder these
+    pub fn id(self) -> u8 {
+        match self {
+            Item::Block(block)         => block.id(),
+            Item::Tool(Tool::Pickaxe)  => FIRST_TOOL_ID,
+            Item::Tool(Tool::Axe)      => FIRST_TOOL_ID + 1,
+            Item::Tool(Tool::Shovel)   => FIRST_TOOL_ID + 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Item> {
+        match id {
+            128 => Some(Item::Tool(Tool::Pickaxe)),
+            129 => Some(Item::Tool(Tool::Axe)),
+            130 => Some(Item::Tool(Tool::Shovel)),
+            _ if id < FIRST_TOOL_ID => Block::from_id(id).map(Item::Block),
+            _ => None,
+        }
+    }
+
+    // the name printed in lower case, as used in data files
+    pub fn from_name(name: &str) -> Option<Item> {
+        (0..256).filter_map(|id| Item::from_id(id as u8))
+            .find(|item| item.to_string().to_lowercase() == name)
+    }
+
+    // tools don't stack
+    pub fn max_stack(self) -> u8 {
+        match self {
+            Item::Block(_) => 64,
+            Item::Tool(_)  => 1,
+        }
+    }
+
+    // index into the block texture array, for dropped items
+    pub fn texture(self, face: Face) -> u8 {
+        match self {
+            Item::Block(block)        => block.texture(face, BlockState::new()),
+            Item::Tool(Tool::Pickaxe) => Block::Stone.texture(face, BlockState::new()),
+            Item::Tool(Tool::Axe)     => Block::Log.texture(face, BlockState::new()),
+            Item::Tool(Tool::Shovel)  => Block::Dirt.texture(face, BlockState::new()),
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Item::Block(block) => write!(f, "{:?}", block),
+            Item::Tool(tool)   => write!(f, "{:?}", tool),
+        }
+    }
+}
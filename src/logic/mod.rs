@@ -1,4 +1,21 @@
+pub mod biome;
+pub mod carver;
 pub mod chunk;
 pub mod chunks;
+pub mod command;
+pub mod crafting;
+pub mod entity;
+pub mod events;
 pub mod block;
+pub mod feature;
 pub mod game;
+pub mod generator;
+pub mod inventory;
+pub mod item;
+pub mod noise;
+pub mod player;
+pub mod plugin;
+pub mod storage;
+#[cfg(test)]
+pub mod testing;
+pub mod world;
@@ -0,0 +1,101 @@
+// seeded value noise, the same seed and position always give the same value
+
+// a well mixed 64 bit value for a lattice position
+pub fn hash(seed: u64, x: i32, y: i32, z: i32) -> u64 {
+    let mut h = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (z as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    // splitmix64 finalizer
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+// uniform in 0..1
+pub fn hash_unit(seed: u64, x: i32, y: i32, z: i32) -> f32 {
+    (hash(seed, x, y, z) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Noise {
+    seed: u64,
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Noise {
+        Noise {
+            seed: seed,
+        }
+    }
+
+    // a different, independent noise from the same seed
+    pub fn derive(&self, salt: u64) -> Noise {
+        Noise::new(hash(self.seed ^ salt, 0, 0, 0))
+    }
+
+    // in -1..1
+    fn lattice(&self, x: i32, y: i32, z: i32) -> f32 {
+        hash_unit(self.seed, x, y, z) * 2.0 - 1.0
+    }
+
+    // smoothly interpolated lattice values, in -1..1
+    pub fn noise2(&self, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+        let (x0, z0) = (x0 as i32, z0 as i32);
+        lerp(
+            lerp(self.lattice(x0, 0, z0    ), self.lattice(x0 + 1, 0, z0    ), tx),
+            lerp(self.lattice(x0, 0, z0 + 1), self.lattice(x0 + 1, 0, z0 + 1), tx),
+            tz,
+        )
+    }
+
+    // smoothly interpolated lattice values, in -1..1
+    pub fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (tx, ty, tz) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+        let (x0, y0, z0) = (x0 as i32, y0 as i32, z0 as i32);
+        let layer = |y| lerp(
+            lerp(self.lattice(x0, y, z0    ), self.lattice(x0 + 1, y, z0    ), tx),
+            lerp(self.lattice(x0, y, z0 + 1), self.lattice(x0 + 1, y, z0 + 1), tx),
+            tz,
+        );
+        lerp(layer(y0), layer(y0 + 1), ty)
+    }
+
+    // octaves of noise2, each with double the frequency and half the amplitude, in -1..1
+    pub fn fractal2(&self, x: f32, z: f32, octaves: u32) -> f32 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for octave in 0..octaves {
+            // every octave gets its own offset so the lattice points don't line up
+            let offset = octave as f32 * 17.31;
+            sum += self.noise2(x * freq + offset, z * freq + offset) * amp;
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / norm
+    }
+
+    // octaves of noise3, each with double the frequency and half the amplitude, in -1..1
+    pub fn fractal3(&self, x: f32, y: f32, z: f32, octaves: u32) -> f32 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for octave in 0..octaves {
+            let offset = octave as f32 * 17.31;
+            sum += self.noise3(x * freq + offset, y * freq + offset, z * freq + offset) * amp;
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / norm
+    }
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -0,0 +1,382 @@
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ Error as IOError, ErrorKind, Read, Write };
+use std::path::Path;
+
+use cgmath::{ Point3, Vector3 };
+
+use config::{ self, LineError };
+use types::Aabb;
+use super::block::Block;
+use super::chunks::Chunks;
+use super::entity::{ self, TICK, GRAVITY };
+use super::inventory::{ Inventory, Stack };
+use super::item::Item;
+use super::world::{ self, GameRules, WorldError };
+
+// height of the eyes above the feet
+pub const EYE_HEIGHT: f32 = 1.62;
+const WIDTH: f32 = 0.6;
+const HEIGHT: f32 = 1.8;
+
+pub const MAX_HEALTH: f32 = 20.0;
+pub const MAX_HUNGER: f32 = 20.0;
+// ticks the player can stay under water
+pub const MAX_AIR: u32 = 300;
+// in blocks per second
+const WALK_SPEED: f32 = 4.3;
+const SWIM_SPEED: f32 = 2.0;
+const JUMP_SPEED: f32 = 7.8;
+// creative and spectator flight
+const FLY_SPEED: f32 = 10.0;
+// falls up to this many blocks don't hurt, every block above costs one health
+const SAFE_FALL: f32 = 3.0;
+// hunger lost per tick, a full bar lasts twenty minutes
+const HUNGER_RATE: f32 = MAX_HUNGER / 24000.0;
+// every this many ticks the player heals when well fed and starves when hungry
+const HEAL_TICKS: u64 = 80;
+const DROWN_TICKS: u64 = 20;
+const DROWN_DAMAGE: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameMode {
+    // physics, mining time and the inventory
+    Survival,
+    // flying, instant breaking and unlimited blocks
+    Creative,
+    // flying through blocks without touching anything
+    Spectator,
+}
+
+impl GameMode {
+    pub fn values() -> [GameMode; 3] {
+        [GameMode::Survival, GameMode::Creative, GameMode::Spectator]
+    }
+
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        GameMode::values().iter().cloned().find(|mode| mode.to_string() == name.to_lowercase())
+    }
+
+    pub fn next(self) -> GameMode {
+        use self::GameMode::*;
+        match self {
+            Survival  => Creative,
+            Creative  => Spectator,
+            Spectator => Survival,
+        }
+    }
+
+    pub fn can_interact(self) -> bool {
+        self != GameMode::Spectator
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        use self::GameMode::*;
+        write!(fmt, "{}", match *self {
+            Survival  => "survival",
+            Creative  => "creative",
+            Spectator => "spectator",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Player {
+    // world coordinates of the feet
+    pub pos: Point3<f32>,
+    // in blocks per second
+    pub velocity: Vector3<f32>,
+    pub on_ground: bool,
+    pub health: f32,
+    pub hunger: f32,
+    // ticks left under water before drowning starts
+    pub air: u32,
+    pub inventory: Inventory,
+    pub mode: GameMode,
+    // where the player looks, in degrees like the camera
+    pub phi: f32,
+    pub theta: f32,
+    // where the player wants to go, horizontal part in -1..1, up to jump, swim or fly
+    pub input: Vector3<f32>,
+}
+
+impl Player {
+    pub fn new(pos: Point3<f32>) -> Player {
+        Player {
+            pos: pos,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            on_ground: false,
+            health: MAX_HEALTH,
+            hunger: MAX_HUNGER,
+            air: MAX_AIR,
+            inventory: Inventory::new(),
+            mode: GameMode::Survival,
+            phi: 0.0,
+            theta: 0.0,
+            input: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        Point3::new(self.pos.x, self.pos.y + EYE_HEIGHT, self.pos.z)
+    }
+
+    pub fn set_eye(&mut self, eye: Point3<f32>) {
+        self.pos = Point3::new(eye.x, eye.y - EYE_HEIGHT, eye.z);
+    }
+
+    pub fn bbox(&self) -> Aabb {
+        let r = WIDTH / 2.0;
+        Aabb::new(
+            Point3::new(self.pos.x - r, self.pos.y, self.pos.z - r),
+            Point3::new(self.pos.x + r, self.pos.y + HEIGHT, self.pos.z + r),
+        )
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    // back to full health at pos, the game mode is kept
+    pub fn respawn(&mut self, pos: Point3<f32>, keep_inventory: bool) {
+        let inventory = self.inventory.clone();
+        let mode = self.mode;
+        *self = Player::new(pos);
+        if keep_inventory {
+            self.inventory = inventory;
+        }
+        self.mode = mode;
+    }
+
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+        self.on_ground = false;
+        self.air = MAX_AIR;
+    }
+
+    pub fn tick(&mut self, chunks: &Chunks, tick: u64, rules: &GameRules) {
+        match self.mode {
+            GameMode::Survival  => self.walk(chunks, tick, rules),
+            GameMode::Creative  => {
+                self.velocity = self.input * FLY_SPEED;
+                let (offset, on_ground, _) = entity::move_box(chunks, &self.bbox(), &mut self.velocity);
+                self.pos = self.pos + offset;
+                self.on_ground = on_ground;
+            },
+            GameMode::Spectator => {
+                self.velocity = self.input * FLY_SPEED;
+                self.pos = self.pos + self.velocity * TICK;
+            },
+        }
+    }
+
+    // one tick of walking, falling and the survival stats
+    fn walk(&mut self, chunks: &Chunks, tick: u64, rules: &GameRules) {
+        let in_water = self.block_at(chunks, self.pos) == Block::Water;
+        let eye_in_water = self.block_at(chunks, self.eye()) == Block::Water;
+
+        let speed = if in_water { SWIM_SPEED } else { WALK_SPEED };
+        self.velocity.x = self.input.x * speed;
+        self.velocity.z = self.input.z * speed;
+        if in_water {
+            // water slows the fall and lets the player swim up
+            self.velocity.y = if self.input.y > 0.0 { SWIM_SPEED } else { (self.velocity.y - GRAVITY * TICK).max(-SWIM_SPEED) };
+        } else {
+            if self.input.y > 0.0 && self.on_ground {
+                self.velocity.y = JUMP_SPEED;
+            }
+            self.velocity.y -= GRAVITY * TICK;
+        }
+
+        let falling = -self.velocity.y;
+        let was_on_ground = self.on_ground;
+        let (offset, on_ground, _) = entity::move_box(chunks, &self.bbox(), &mut self.velocity);
+        self.pos = self.pos + offset;
+        self.on_ground = on_ground;
+
+        // the height of the fall follows from the speed at landing
+        if rules.fall_damage && on_ground && !was_on_ground && !in_water && falling > 0.0 {
+            let height = falling * falling / (2.0 * GRAVITY);
+            if height > SAFE_FALL {
+                self.health -= (height - SAFE_FALL).floor();
+            }
+        }
+
+        if eye_in_water {
+            if self.air > 0 {
+                self.air -= 1;
+            } else if tick % DROWN_TICKS == 0 {
+                self.health -= DROWN_DAMAGE;
+            }
+        } else {
+            self.air = MAX_AIR;
+        }
+
+        self.hunger = (self.hunger - HUNGER_RATE).max(0.0);
+        if tick % HEAL_TICKS == 0 {
+            if self.hunger >= MAX_HUNGER * 0.9 {
+                self.health = (self.health + 1.0).min(MAX_HEALTH);
+            } else if self.hunger <= 0.0 {
+                self.health -= 1.0;
+            }
+        }
+    }
+
+    fn block_at(&self, chunks: &Chunks, p: Point3<f32>) -> Block {
+        let (chunk, block) = entity::split(Point3::new(p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32));
+        chunks[chunk][block]
+    }
+
+    // what gets saved, `key = value` per line like the config, one line per filled slot
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str(&format!("pos = {} {} {}\n", self.pos.x, self.pos.y, self.pos.z));
+        text.push_str(&format!("phi = {}\n", self.phi));
+        text.push_str(&format!("theta = {}\n", self.theta));
+        text.push_str(&format!("mode = {}\n", self.mode));
+        text.push_str(&format!("health = {}\n", self.health));
+        text.push_str(&format!("hunger = {}\n", self.hunger));
+        text.push_str(&format!("air = {}\n", self.air));
+        for (i, slot) in self.inventory.slots().iter().enumerate() {
+            if let Some(stack) = *slot {
+                text.push_str(&format!("slot = {} {} {}\n", i, stack.item.to_string().to_lowercase(), stack.count));
+            }
+        }
+        text
+    }
+
+    // a missing file leaves the player as it is, a broken one is refused
+    // so the next save doesn't overwrite what couldn't be read
+    pub fn load(&mut self, path: &Path) -> Result<(), WorldError> {
+        let mut text = String::new();
+        match File::open(path) {
+            Ok(mut file) => { try!(file.read_to_string(&mut text)); },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(WorldError::ReadError(e)),
+        }
+        self.parse(&text).map_err(|e| WorldError::Invalid(path.display().to_string(), e))
+    }
+
+    // applies what to_text wrote, up to the first broken line
+    pub fn parse(&mut self, text: &str) -> Result<(), LineError> {
+        let errors = config::parse_lines(text, |key, value| self.set(key, value));
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), IOError> {
+        if let Some(dir) = path.parent() {
+            try!(fs::create_dir_all(dir));
+        }
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(self.to_text().as_bytes()));
+        }
+        fs::rename(tmp, path)
+    }
+
+    // `<index> <item> <count>`
+    fn parse_slot(&mut self, value: &str) -> Result<(), String> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(format!("expected `slot = <index> <item> <count>`, found `{}`", value));
+        }
+        let index: usize = try!(parts[0].parse().map_err(|_| format!("invalid slot `{}`", parts[0])));
+        let item = try!(Item::from_name(parts[1]).ok_or(format!("unknown item `{}`", parts[1])));
+        let count: u8 = try!(parts[2].parse().map_err(|_| format!("invalid count `{}`", parts[2])));
+        if count == 0 || count > item.max_stack() {
+            return Err(format!("invalid count `{}` for {}", count, parts[1]));
+        }
+        if !self.inventory.set_slot(index, Some(Stack { item: item, count: count })) {
+            return Err(format!("there is no slot {}", index));
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "pos" => self.pos = try!(world::parse_point(value).ok_or(format!("invalid pos `{}`", value))),
+            "phi" => self.phi = try!(config::parse_value(key, value)),
+            "theta" => self.theta = try!(config::parse_value(key, value)),
+            "mode" => self.mode = try!(GameMode::from_name(value).ok_or(format!("unknown game mode `{}`", value))),
+            "health" => self.health = try!(config::parse_value(key, value)),
+            "hunger" => self.hunger = try!(config::parse_value(key, value)),
+            "air" => self.air = try!(config::parse_value(key, value)),
+            "slot" => try!(self.parse_slot(value)),
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use super::*;
+    use logic::block::Block;
+    use logic::inventory::Stack;
+    use logic::item::{ Item, Tool };
+
+    #[test]
+    fn saved_players_load_again() {
+        let mut player = Player::new(Point3::new(1.5, 64.0, -2.25));
+        player.phi = 90.0;
+        player.theta = -30.5;
+        player.mode = GameMode::Creative;
+        player.health = 7.0;
+        player.hunger = 12.5;
+        player.air = 40;
+        player.inventory.add(Item::Block(Block::Stone), 70);
+        player.inventory.add(Item::Tool(Tool::Pickaxe), 1);
+
+        let mut loaded = Player::new(Point3::new(0.0, 0.0, 0.0));
+        loaded.parse(&player.to_text()).unwrap();
+        assert_eq!(loaded.pos, player.pos);
+        assert_eq!(loaded.phi, 90.0);
+        assert_eq!(loaded.theta, -30.5);
+        assert_eq!(loaded.mode, GameMode::Creative);
+        assert_eq!(loaded.health, 7.0);
+        assert_eq!(loaded.hunger, 12.5);
+        assert_eq!(loaded.air, 40);
+        assert_eq!(loaded.inventory.slots(), player.inventory.slots());
+    }
+
+    #[test]
+    fn slots_keep_their_place() {
+        let mut player = Player::new(Point3::new(0.0, 0.0, 0.0));
+        player.parse("slot = 35 dirt 3\n").unwrap();
+        assert_eq!(player.inventory.slots()[35], Some(Stack { item: Item::Block(Block::Dirt), count: 3 }));
+        assert_eq!(player.inventory.count(Item::Block(Block::Dirt)), 3);
+    }
+
+    #[test]
+    fn broken_slots_are_refused() {
+        let broken = [
+            "slot = 1 dirt",
+            "slot = 36 dirt 1",
+            "slot = 0 diamond 1",
+            "slot = 0 dirt 0",
+            "slot = 0 dirt 65",
+            "slot = 0 pickaxe 2",
+        ];
+        for line in broken.iter() {
+            let mut player = Player::new(Point3::new(0.0, 0.0, 0.0));
+            assert!(player.parse(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn the_first_broken_line_is_returned() {
+        let mut player = Player::new(Point3::new(0.0, 0.0, 0.0));
+        let e = player.parse("health = 5\n\nmode = hardcore\nhunger = x\n").unwrap_err();
+        assert_eq!(e, LineError::new(3, "unknown game mode `hardcore`".to_string()));
+        assert_eq!(player.health, 5.0);
+    }
+}
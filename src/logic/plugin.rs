@@ -0,0 +1,99 @@
+use cgmath::Point3;
+
+use super::block::{ Block, BlockState };
+use super::events::GameEvent;
+use super::game::GameState;
+use super::item::Item;
+use super::player::GameMode;
+
+// behaviour added to the game without changing it, registered with GameState::register_plugin.
+// Hooks run in registration order. The ones returning bool cancel the action with false,
+// later plugins don't see a cancelled action then. What a hook does to the game, like
+// GameState::set_block, goes through the hooks of every other plugin before it returns.
+pub trait Plugin {
+    fn name(&self) -> &str;
+
+    // once after all plugins are registered
+    fn world_load(&mut self, _game: &mut GameState) {}
+
+    // right after the player got loaded, see world_load
+    fn player_join(&mut self, _game: &mut GameState) {}
+
+    // at the start of every tick, before anything moves
+    fn tick(&mut self, _game: &mut GameState, _tick: u64) {}
+
+    // drops can be changed, pos is in world coordinates
+    fn block_break(&mut self, _game: &mut GameState, _pos: Point3<i32>, _block: Block, _drops: &mut Vec<(Item, u8)>) -> bool {
+        true
+    }
+
+    // what gets placed can be changed
+    fn block_place(&mut self, _game: &mut GameState, _pos: Point3<i32>, _block: &mut Block, _state: &mut BlockState) -> bool {
+        true
+    }
+
+    // every event at the end of the tick it happened in, see EventBus
+    fn event(&mut self, _game: &mut GameState, _event: &GameEvent) {}
+
+    // names of the console commands this plugin handles
+    fn commands(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    // runs one of the commands, args are the words after the name
+    fn command(&mut self, _game: &mut GameState, name: &str, _args: &[&str]) -> Result<String, String> {
+        Err(format!("unknown command `{}`", name))
+    }
+}
+
+// example plugin, nobody but creative players can build close to the spawn point
+pub struct SpawnProtection {
+    // in blocks, horizontally around the spawn
+    radius: i32,
+    center: Point3<i32>,
+}
+
+impl SpawnProtection {
+    pub fn new(radius: i32) -> SpawnProtection {
+        SpawnProtection {
+            radius: radius,
+            center: Point3::new(0, 0, 0),
+        }
+    }
+
+    fn protects(&self, game: &GameState, pos: Point3<i32>) -> bool {
+        let (dx, dz) = (pos.x - self.center.x, pos.z - self.center.z);
+        game.get_game_mode() != GameMode::Creative && dx.abs() <= self.radius && dz.abs() <= self.radius
+    }
+}
+
+impl Plugin for SpawnProtection {
+    fn name(&self) -> &str {
+        "spawn protection"
+    }
+
+    fn world_load(&mut self, game: &mut GameState) {
+        let spawn = game.get_spawn();
+        self.center = Point3::new(spawn.x.floor() as i32, spawn.y.floor() as i32, spawn.z.floor() as i32);
+    }
+
+    fn block_break(&mut self, game: &mut GameState, pos: Point3<i32>, _: Block, _: &mut Vec<(Item, u8)>) -> bool {
+        !self.protects(game, pos)
+    }
+
+    fn block_place(&mut self, game: &mut GameState, pos: Point3<i32>, _: &mut Block, _: &mut BlockState) -> bool {
+        !self.protects(game, pos)
+    }
+
+    fn commands(&self) -> Vec<&'static str> {
+        vec!["protect"]
+    }
+
+    // `/protect` shows the radius, `/protect <radius>` changes it
+    fn command(&mut self, _: &mut GameState, _: &str, args: &[&str]) -> Result<String, String> {
+        if let Some(radius) = args.first() {
+            self.radius = try!(radius.parse().map_err(|_| format!("invalid radius `{}`", radius)));
+        }
+        Ok(format!("spawn protection radius: {}", self.radius))
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::{ self, File };
+use std::io::{ Error as IOError, ErrorKind, Read, Write };
+use std::path::PathBuf;
+
+use super::chunk::Chunk;
+use super::chunks::ChunkPos;
+use super::entity::Entity;
+
+// chunks on disk, one file per chunk named after its position,
+// the entities in a chunk are kept in a second file next to it
+#[derive(Debug)]
+pub struct Storage {
+    dir: PathBuf,
+}
+
+impl Storage {
+    pub fn new<P: Into<PathBuf>>(world_dir: P) -> Storage {
+        Storage {
+            dir: world_dir.into().join("chunks"),
+        }
+    }
+
+    fn path(&self, pos: ChunkPos) -> PathBuf {
+        self.dir.join(format!("{}.{}.{}.chunk", pos.x, pos.y, pos.z))
+    }
+
+    fn entities_path(&self, pos: ChunkPos) -> PathBuf {
+        self.path(pos).with_extension("entities")
+    }
+
+    // Ok(None) if the chunk was never saved
+    pub fn load(&self, pos: ChunkPos) -> Result<Option<Chunk>, IOError> {
+        let mut file = match File::open(self.path(pos)) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut bytes = Vec::new();
+        try!(file.read_to_end(&mut bytes));
+        match Chunk::from_bytes(&bytes) {
+            Some(chunk) => Ok(Some(chunk)),
+            None => Err(IOError::new(ErrorKind::InvalidData, format!("corrupt chunk file {}", self.path(pos).display()))),
+        }
+    }
+
+    pub fn save(&self, pos: ChunkPos, chunk: &Chunk) -> Result<(), IOError> {
+        self.write(self.path(pos), &chunk.to_bytes())
+    }
+
+    // empty if there never were any entities in the chunk
+    pub fn load_entities(&self, pos: ChunkPos) -> Result<Vec<Entity>, IOError> {
+        let path = self.entities_path(pos);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut bytes = Vec::new();
+        try!(file.read_to_end(&mut bytes));
+        let mut entities = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            match Entity::from_bytes(&bytes, &mut i) {
+                Some(entity) => entities.push(entity),
+                None => return Err(IOError::new(ErrorKind::InvalidData, format!("corrupt entity file {}", path.display()))),
+            }
+        }
+        Ok(entities)
+    }
+
+    // no entities means no file
+    pub fn save_entities(&self, pos: ChunkPos, entities: &[Entity]) -> Result<(), IOError> {
+        if entities.is_empty() {
+            return match fs::remove_file(self.entities_path(pos)) {
+                Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+                res => res,
+            };
+        }
+        let mut bytes = Vec::new();
+        for entity in entities {
+            entity.to_bytes(&mut bytes);
+        }
+        self.write(self.entities_path(pos), &bytes)
+    }
+
+    fn write(&self, path: PathBuf, bytes: &[u8]) -> Result<(), IOError> {
+        try!(fs::create_dir_all(&self.dir));
+        // write to a temporary file first, so a crash never leaves half a file behind
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(bytes));
+        }
+        fs::rename(tmp, path)
+    }
+}
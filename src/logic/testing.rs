@@ -0,0 +1,34 @@
+use std::env;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::process;
+use std::sync::atomic::{ AtomicUsize, Ordering, ATOMIC_USIZE_INIT };
+
+static NEXT_DIR: AtomicUsize = ATOMIC_USIZE_INIT;
+
+// an empty directory of its own for a test, removed again when it is dropped,
+// also when the test panics
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    // the process id and a counter keep parallel and repeated runs apart
+    pub fn new(name: &str) -> TempDir {
+        let n = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("rusteezee-{}-{}-{}", name, process::id(), n));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path: path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,360 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ Error as IOError, ErrorKind, Read, Write };
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use cgmath::Point3;
+
+use config::{ self, Config, LineError };
+use super::carver::CarverSettings;
+
+// written into every world, bump it when the saved data changes and add a step to upgrade.
+// 1: chunks and entities only, seed and caves came from the config
+// 2: world.txt with the seed, generator settings, spawn, time and game rules
+pub const FORMAT_VERSION: u32 = 2;
+
+const INFO_FILE: &'static str = "world.txt";
+
+#[derive(Clone, Debug)]
+pub struct GameRules {
+    // dying doesn't empty the inventory
+    pub keep_inventory: bool,
+    pub fall_damage: bool,
+    // freshly generated chunks get mobs
+    pub mob_spawning: bool,
+}
+
+impl GameRules {
+    pub fn new() -> GameRules {
+        GameRules {
+            keep_inventory: true,
+            fall_damage: true,
+            mob_spawning: true,
+        }
+    }
+}
+
+// everything about a saved world that isn't in its chunks, kept in world.txt
+// as one `key = value` per line like the config
+#[derive(Clone, Debug)]
+pub struct WorldInfo {
+    pub format: u32,
+    pub seed: u64,
+    pub caves: CarverSettings,
+    // world coordinates of the feet, None until the generator chose one for a new world
+    pub spawn: Option<Point3<f32>>,
+    // ticks the world has run
+    pub time: u64,
+    // seconds since the unix epoch, when the world was saved last
+    pub last_played: u64,
+    pub rules: GameRules,
+}
+
+impl WorldInfo {
+    // a new world with the generator settings from the config
+    pub fn new(config: &Config) -> WorldInfo {
+        WorldInfo {
+            format: FORMAT_VERSION,
+            seed: config.seed,
+            caves: config.caves.clone(),
+            spawn: None,
+            time: 0,
+            last_played: 0,
+            rules: GameRules::new(),
+        }
+    }
+
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(INFO_FILE)
+    }
+
+    // the info of the world in dir, worlds of older formats get upgraded on the way,
+    // newer ones are refused before anything in them gets touched
+    pub fn load(dir: &Path, config: &Config) -> Result<WorldInfo, WorldError> {
+        let mut text = String::new();
+        match File::open(WorldInfo::path(dir)) {
+            Ok(mut file) => { try!(file.read_to_string(&mut text)); },
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                let mut info = WorldInfo::new(config);
+                // chunks without world.txt were saved before it existed
+                if dir.join("chunks").exists() {
+                    info.format = 1;
+                    info.upgrade();
+                }
+                return Ok(info);
+            },
+            Err(e) => return Err(WorldError::ReadError(e)),
+        }
+        WorldInfo::parse(&text, config)
+    }
+
+    // the contents of world.txt, the config fills in what older formats didn't save
+    pub fn parse(text: &str, config: &Config) -> Result<WorldInfo, WorldError> {
+        let invalid = |e: LineError| WorldError::Invalid(INFO_FILE.to_string(), e);
+        let mut info = WorldInfo::new(config);
+        // the format decides how to read the rest, so it comes first
+        info.format = 0;
+        for (n, line) in text.lines().enumerate() {
+            if let Ok(Some(("format", value))) = config::split_line(line) {
+                info.format = try!(config::parse_value("format", value).map_err(|e| invalid(LineError::new(n + 1, e))));
+            }
+        }
+        if info.format == 0 {
+            return Err(invalid(LineError::new(0, "the format version is missing".to_string())));
+        }
+        if info.format > FORMAT_VERSION {
+            return Err(WorldError::TooNew(info.format));
+        }
+        let errors = config::parse_lines(text, |key, value| info.set(key, value));
+        if let Some(e) = errors.into_iter().next() {
+            return Err(invalid(e));
+        }
+        info.upgrade();
+        Ok(info)
+    }
+
+    // brings the info of an older world up to FORMAT_VERSION, one step at a time
+    fn upgrade(&mut self) {
+        while self.format < FORMAT_VERSION {
+            // 1 to 2: the seed and the caves were taken from the config, which is what
+            // the world got generated with, everything else starts out at the defaults
+            self.format += 1;
+        }
+    }
+
+    pub fn save(&mut self, dir: &Path) -> Result<(), IOError> {
+        self.last_played = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        try!(fs::create_dir_all(dir));
+        let path = WorldInfo::path(dir);
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(self.to_text().as_bytes()));
+        }
+        fs::rename(tmp, path)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str(&format!("format = {}\n", self.format));
+        text.push_str(&format!("seed = {}\n", self.seed));
+        text.push_str(&format!("cave_frequency = {}\n", self.caves.cave_frequency));
+        text.push_str(&format!("cave_threshold = {}\n", self.caves.cave_threshold));
+        text.push_str(&format!("tunnel_chance = {}\n", self.caves.worm_chance));
+        text.push_str(&format!("ravine_chance = {}\n", self.caves.ravine_chance));
+        text.push_str(&format!("tunnel_radius = {}\n", self.caves.worm_radius));
+        text.push_str(&format!("tunnel_length = {}\n", self.caves.worm_length));
+        text.push_str(&format!("cave_min_depth = {}\n", self.caves.min_depth));
+        text.push_str(&format!("cave_lowest = {}\n", self.caves.lowest));
+        if let Some(spawn) = self.spawn {
+            text.push_str(&format!("spawn = {} {} {}\n", spawn.x, spawn.y, spawn.z));
+        }
+        text.push_str(&format!("time = {}\n", self.time));
+        text.push_str(&format!("last_played = {}\n", self.last_played));
+        text.push_str(&format!("keep_inventory = {}\n", self.rules.keep_inventory));
+        text.push_str(&format!("fall_damage = {}\n", self.rules.fall_damage));
+        text.push_str(&format!("mob_spawning = {}\n", self.rules.mob_spawning));
+        text
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "format" => {},
+            "seed" => self.seed = try!(config::parse_value(key, value)),
+            "cave_frequency" => self.caves.cave_frequency = try!(config::parse_value(key, value)),
+            "cave_threshold" => self.caves.cave_threshold = try!(config::parse_value(key, value)),
+            "tunnel_chance" => self.caves.worm_chance = try!(config::parse_value(key, value)),
+            "ravine_chance" => self.caves.ravine_chance = try!(config::parse_value(key, value)),
+            "tunnel_radius" => self.caves.worm_radius = try!(config::parse_value(key, value)),
+            "tunnel_length" => self.caves.worm_length = try!(config::parse_value(key, value)),
+            "cave_min_depth" => self.caves.min_depth = try!(config::parse_value(key, value)),
+            "cave_lowest" => self.caves.lowest = try!(config::parse_value(key, value)),
+            "spawn" => self.spawn = Some(try!(parse_point(value).ok_or(format!("invalid spawn `{}`", value)))),
+            "time" => self.time = try!(config::parse_value(key, value)),
+            "last_played" => self.last_played = try!(config::parse_value(key, value)),
+            "keep_inventory" => self.rules.keep_inventory = try!(config::parse_value(key, value)),
+            "fall_damage" => self.rules.fall_damage = try!(config::parse_value(key, value)),
+            "mob_spawning" => self.rules.mob_spawning = try!(config::parse_value(key, value)),
+            _ => return Err(format!("unknown key `{}`", key)),
+        }
+        Ok(())
+    }
+}
+
+// three numbers separated by spaces
+pub fn parse_point(value: &str) -> Option<Point3<f32>> {
+    let coords: Vec<f32> = match value.split_whitespace().map(|v| v.parse()).collect() {
+        Ok(coords) => coords,
+        Err(_) => return None,
+    };
+    if coords.len() == 3 {
+        Some(Point3::new(coords[0], coords[1], coords[2]))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum WorldError {
+    ReadError(IOError),
+    WriteError(IOError),
+    // the file and what is wrong in it
+    Invalid(String, LineError),
+    // the format version the world was written with
+    TooNew(u32),
+}
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        use self::WorldError::*;
+        match *self {
+            ReadError(ref s) | WriteError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
+            Invalid(ref file, ref e) if e.line == 0 =>
+                write!(fmt, "{}: {}: {}", self.description(), file, e.message),
+            Invalid(ref file, ref e) =>
+                write!(fmt, "{}: {}:{}", self.description(), file, e),
+            TooNew(format) =>
+                write!(fmt, "{}: it has format {}, this version only knows up to {}", self.description(), format, FORMAT_VERSION),
+        }
+    }
+}
+
+impl Error for WorldError {
+    fn description(&self) -> &str {
+        use self::WorldError::*;
+        match *self {
+            ReadError(_) =>
+                "Could not read the world",
+            WriteError(_) =>
+                "Could not save the world",
+            Invalid(..) =>
+                "The world is broken",
+            TooNew(_) =>
+                "The world was saved by a newer version of the game",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&Error> {
+        use self::WorldError::*;
+        match *self {
+            ReadError(ref s) => Some(s),
+            WriteError(ref s) => Some(s),
+            Invalid(..) => None,
+            TooNew(_) => None,
+        }
+    }
+}
+
+impl From<IOError> for WorldError {
+    fn from(err: IOError) -> Self {
+        WorldError::ReadError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use cgmath::Point3;
+
+    use config::{ Config, LineError };
+    use logic::testing::TempDir;
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        config.seed = 7;
+        config
+    }
+
+    #[test]
+    fn a_new_world_takes_the_config() {
+        let dir = TempDir::new("world-new");
+        let info = WorldInfo::load(dir.path(), &config()).unwrap();
+        assert_eq!(info.format, FORMAT_VERSION);
+        assert_eq!(info.seed, 7);
+        assert_eq!(info.spawn, None);
+        assert_eq!(info.time, 0);
+    }
+
+    #[test]
+    fn chunks_without_world_txt_get_upgraded() {
+        let dir = TempDir::new("world-legacy");
+        fs::create_dir_all(dir.path().join("chunks")).unwrap();
+        let info = WorldInfo::load(dir.path(), &config()).unwrap();
+        assert_eq!(info.format, FORMAT_VERSION);
+        assert_eq!(info.seed, 7);
+    }
+
+    #[test]
+    fn saved_info_loads_again() {
+        let dir = TempDir::new("world-save");
+        let mut info = WorldInfo::new(&config());
+        info.seed = 12345;
+        info.spawn = Some(Point3::new(0.5, 70.0, -3.25));
+        info.time = 6000;
+        info.rules.keep_inventory = false;
+        info.save(dir.path()).unwrap();
+        assert!(info.last_played > 0);
+
+        let loaded = WorldInfo::load(dir.path(), &Config::new()).unwrap();
+        assert_eq!(loaded.format, FORMAT_VERSION);
+        assert_eq!(loaded.seed, 12345);
+        assert_eq!(loaded.spawn, Some(Point3::new(0.5, 70.0, -3.25)));
+        assert_eq!(loaded.time, 6000);
+        assert_eq!(loaded.last_played, info.last_played);
+        assert!(!loaded.rules.keep_inventory);
+        assert!(loaded.rules.fall_damage);
+    }
+
+    #[test]
+    fn older_formats_get_upgraded() {
+        let info = WorldInfo::parse("format = 1\nseed = 3\n", &config()).unwrap();
+        assert_eq!(info.format, FORMAT_VERSION);
+        assert_eq!(info.seed, 3);
+    }
+
+    #[test]
+    fn newer_formats_are_refused() {
+        let text = format!("seed = 3\nformat = {}\nunknown = 1\n", FORMAT_VERSION + 1);
+        match WorldInfo::parse(&text, &config()) {
+            Err(WorldError::TooNew(format)) => assert_eq!(format, FORMAT_VERSION + 1),
+            other => panic!("expected TooNew, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn the_format_is_required() {
+        match WorldInfo::parse("seed = 3\n", &config()) {
+            Err(WorldError::Invalid(file, e)) => {
+                assert_eq!(file, "world.txt");
+                assert_eq!(e.line, 0);
+            },
+            other => panic!("expected Invalid, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn broken_lines_are_fatal() {
+        match WorldInfo::parse("format = 2\nseed = 3\nspawn = 1 2\n", &config()) {
+            Err(WorldError::Invalid(_, e)) => assert_eq!(e, LineError::new(3, "invalid spawn `1 2`".to_string())),
+            other => panic!("expected Invalid, got {:?}", other),
+        }
+        match WorldInfo::parse("format = two\n", &config()) {
+            Err(WorldError::Invalid(_, e)) => assert_eq!(e.line, 1),
+            other => panic!("expected Invalid, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn points_need_three_numbers() {
+        assert_eq!(parse_point("1 -2.5 3"), Some(Point3::new(1.0, -2.5, 3.0)));
+        assert_eq!(parse_point("1 2"), None);
+        assert_eq!(parse_point("1 2 3 4"), None);
+        assert_eq!(parse_point("1 x 3"), None);
+    }
+}
@@ -7,16 +7,36 @@ extern crate cgmath;
 extern crate image;
 extern crate bit_set;
 
+mod config;
 mod event;
 mod logic;
 mod render;
 mod types;
 
+use config::Config;
 use render::renderer::Renderer;
 
 fn main() {
-    match Renderer::new() {
-        Ok(r)  => { r.game_loop(); },
+    // --screenshot renders the first frame to a png and quits
+    let capture = std::env::args().any(|arg| arg == "--screenshot");
+    let (config, problems) = Config::load();
+    for problem in problems {
+        println!("{}", problem);
+    }
+    match Renderer::new(&config) {
+        Ok((r, problems)) => {
+            for problem in problems {
+                println!("{}", problem);
+            }
+            if capture {
+                match r.capture(1) {
+                    Ok(path) => println!("Saved screenshot to {}", path.display()),
+                    Err(e)   => println!("{}", e),
+                }
+            } else {
+                r.game_loop();
+            }
+        },
         Err(r) => { println!("{}", r); },
     }
 }
@@ -5,8 +5,9 @@ use cgmath;
 use cgmath::{ Vector3, Point, Point3, Angle, Deg, Vector, EuclideanVector, Matrix4 };
 use bit_set::BitSet;
 
-use types::{ HDirection, VDirection };
+use types::{ Face, HDirection, VDirection };
 use logic::chunks::ChunkPos;
+use logic::chunk::BlockPos;
 
 const CAM_POS_STEP: f32 = 0.1;
 const CAM_DIR_STEP: Deg<f32> = Deg { s: 0.5 };
@@ -51,6 +52,11 @@ impl Movements {
     }
 
     pub fn to_vec(&self, phi: Deg<f32>) -> Vector3<f32> {
+        self.direction(phi) * CAM_POS_STEP
+    }
+
+    // unit vector of the movement, zero for turns
+    pub fn direction(&self, phi: Deg<f32>) -> Vector3<f32> {
         use self::Movements::*;
         Vector3::from(match *self {
             Up    => (0.0,  1.0, 0.0),
@@ -71,7 +77,7 @@ impl Movements {
                 }
             },
             _ => (0.0, 0.0, 0.0),
-        }) * CAM_POS_STEP
+        })
     }
 
     fn to_angle(&self) -> Option<Deg<f32>> {
@@ -94,11 +100,13 @@ pub struct Camera {
     phi: Deg<f32>,
     theta: Deg<f32>,
     state: BitSet,
+    // moves on its own instead of following the player
+    flying: bool,
 }
 
 impl Camera {
     pub fn new(pos: Point3<f32>, phi: Deg<f32>, theta: Deg<f32>) -> Camera {
-        let mut cam = Camera { pos: pos, chunk: Point::origin(), phi: phi, theta: theta, state: BitSet::new() };
+        let mut cam = Camera { pos: pos, chunk: Point::origin(), phi: phi, theta: theta, state: BitSet::new(), flying: true };
         cam.norm_phi();
         cam.norm_theta();
         cam.update();
@@ -124,13 +132,18 @@ impl Camera {
         self.theta = cgmath::deg(self.theta.s.max(-89.999).min(89.999));
     }
 
-    pub fn view_matrix(&self) -> Matrix4<f32> {
-        // forward
-        let f = Vector3::new(
+    // unit vector in view direction
+    pub fn get_forward(&self) -> Vector3<f32> {
+        Vector3::new(
             self.theta.cos() * self.phi.sin(),
             self.theta.sin(),
             self.theta.cos() * self.phi.cos().neg(),
-        ).normalize();
+        ).normalize()
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        // forward
+        let f = self.get_forward();
         // sideways
         let s = f.cross(UP).normalize();
         // up
@@ -196,10 +209,89 @@ impl Camera {
         }
     }
 
+    // forgets all held keys, e.g. when typing starts
+    pub fn stop(&mut self) {
+        self.state.clear();
+    }
+
+    pub fn set_flying(&mut self, flying: bool) {
+        self.flying = flying;
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.flying
+    }
+
+    // where the pressed keys want to go, horizontal length at most 1, y is -1, 0 or 1
+    pub fn get_movement(&self) -> Vector3<f32> {
+        let mut movement = Vector3::new(0.0, 0.0, 0.0);
+        for dir in Movements::moves() {
+            if self.state.contains(&(dir as usize)) {
+                movement = movement + dir.direction(self.phi);
+            }
+        }
+        let horizontal = Vector3::new(movement.x, 0.0, movement.z);
+        if horizontal.length() > 1.0 {
+            let horizontal = horizontal.normalize();
+            movement.x = horizontal.x;
+            movement.z = horizontal.z;
+        }
+        movement
+    }
+
+    pub fn set_world_pos(&mut self, pos: Point3<f32>) {
+        self.chunk = Point::origin();
+        self.pos = pos;
+        self.normalize_pos();
+    }
+
     pub fn get_chunk_pos(&self) -> ChunkPos {
         self.chunk
     }
 
+    pub fn get_phi(&self) -> Deg<f32> {
+        self.phi
+    }
+
+    pub fn get_theta(&self) -> Deg<f32> {
+        self.theta
+    }
+
+    pub fn set_look(&mut self, phi: Deg<f32>, theta: Deg<f32>) {
+        self.phi = phi;
+        self.theta = theta;
+        self.norm_phi();
+        self.norm_theta();
+    }
+
+    // horizontal direction the camera looks towards
+    pub fn get_facing(&self) -> Face {
+        const FACES: [Face; 4] = [Face::North, Face::East, Face::South, Face::West];
+        FACES[((self.phi.s + 45.0) / 90.0) as usize % 4]
+    }
+
+    // position inside of the current chunk
+    pub fn get_pos(&self) -> Point3<f32> {
+        self.pos
+    }
+
+    pub fn get_world_pos(&self) -> Point3<f32> {
+        Point3::new(
+            (self.chunk.x * 16) as f32 + self.pos.x,
+            (self.chunk.y * 16) as f32 + self.pos.y,
+            (self.chunk.z * 16) as f32 + self.pos.z,
+        )
+    }
+
+    // block the eye is in, pos may be exactly 16.0 on the upper border
+    pub fn get_block_pos(&self) -> BlockPos {
+        Point3::new(
+            (self.pos.x.floor() as u8).min(15),
+            (self.pos.y.floor() as u8).min(15),
+            (self.pos.z.floor() as u8).min(15),
+        )
+    }
+
     pub fn update(&mut self) {
         for turn in Movements::turns() {
             if self.state.contains(&(turn as usize)) {
@@ -215,11 +307,18 @@ impl Camera {
         }
         self.norm_phi();
         self.norm_theta();
-        for dir in Movements::moves() {
-            if self.state.contains(&(dir as usize)) {
-                self.pos = self.pos + dir.to_vec(self.phi);
+        if self.flying {
+            for dir in Movements::moves() {
+                if self.state.contains(&(dir as usize)) {
+                    self.pos = self.pos + dir.to_vec(self.phi);
+                }
             }
         }
+        self.normalize_pos();
+    }
+
+    // moves whole chunks from pos into chunk
+    fn normalize_pos(&mut self) {
         while self.pos.x > 16.0 {
             self.chunk[0] += 1;
             self.pos.x -= 16.0;
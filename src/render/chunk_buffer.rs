@@ -1,17 +1,113 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{ HashMap, HashSet };
 
 use glium::{ Display, VertexBuffer };
-use cgmath::{ Point, Point3 };
+use cgmath::{ Point, Point3, Vector3 };
 
 use render::FaceVertex;
 use logic::game::GameState;
+use logic::chunk::{ BlockPos, Chunk };
 use logic::chunks::{ ChunkPos, Chunks };
+use logic::entity;
+use logic::block::Layer;
+
+// translucent faces get sorted again once the eye moved this many blocks
+const RESORT_DIST: f32 = 1.0;
+
+#[derive(Debug)]
+struct ChunkMesh {
+    // opaque and cutout faces, these don't need any ordering
+    solid: VertexBuffer<FaceVertex>,
+    // translucent faces, sorted back to front as seen from sorted_for
+    translucent: Vec<FaceVertex>,
+    translucent_buffer: VertexBuffer<FaceVertex>,
+    // eye position relative to the chunk origin at the time of the last sort
+    sorted_for: Point3<f32>,
+    // Chunk::version the mesh was built from
+    version: u64,
+}
+
+impl ChunkMesh {
+    fn new(display: &Display, chunk: &Chunk, eye: Point3<f32>) -> ChunkMesh {
+        let mut solid = chunk.as_faces(Layer::Opaque);
+        solid.extend(chunk.as_faces(Layer::Cutout));
+        let mut translucent = chunk.as_faces(Layer::Translucent);
+        ChunkMesh::sort_back_to_front(&mut translucent, eye);
+        ChunkMesh {
+            solid: VertexBuffer::new(display, &solid).unwrap(),
+            translucent_buffer: VertexBuffer::new(display, &translucent).unwrap(),
+            translucent: translucent,
+            sorted_for: eye,
+            version: chunk.version(),
+        }
+    }
+
+    fn resort(&mut self, display: &Display, eye: Point3<f32>) {
+        if self.translucent.is_empty() || distance2(self.sorted_for, eye) < RESORT_DIST * RESORT_DIST {
+            return;
+        }
+        ChunkMesh::sort_back_to_front(&mut self.translucent, eye);
+        self.translucent_buffer = VertexBuffer::new(display, &self.translucent).unwrap();
+        self.sorted_for = eye;
+    }
+
+    // every face consists of two vertices, the faces are sorted by the distance of their middle
+    fn sort_back_to_front(faces: &mut Vec<FaceVertex>, eye: Point3<f32>) {
+        let mut quads: Vec<(f32, FaceVertex, FaceVertex)> = faces.chunks(2).map(|q| {
+            let mid = Point3::new(
+                q[0].pos[0] as f32 + (q[0].corner[0] + q[1].corner[0]) / 2.0,
+                q[0].pos[1] as f32 + (q[0].corner[1] + q[1].corner[1]) / 2.0,
+                q[0].pos[2] as f32 + (q[0].corner[2] + q[1].corner[2]) / 2.0,
+            );
+            (distance2(mid, eye), q[0], q[1])
+        }).collect();
+        quads.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        faces.clear();
+        for (_, a, b) in quads {
+            faces.push(a);
+            faces.push(b);
+        }
+    }
+}
+
+// the chunks next to chunk that block touches, none for blocks inside
+pub fn border_neighbours(chunk: ChunkPos, block: BlockPos) -> Vec<ChunkPos> {
+    let mut neighbours = Vec::new();
+    for axis in 0..3 {
+        let mut d = Vector3::new(0, 0, 0);
+        d[axis] = 1;
+        if block[axis] == 0 {
+            neighbours.push(chunk + -d);
+        } else if block[axis] == 15 {
+            neighbours.push(chunk + d);
+        }
+    }
+    neighbours
+}
+
+// a mesh has to be built again if there is none yet, the chunk changed
+// since it was built or a neighbour changed the faces on the border
+fn outdated(mesh_version: Option<u64>, chunk_version: u64, stale: bool) -> bool {
+    match mesh_version {
+        Some(version) => version != chunk_version || stale,
+        None => true,
+    }
+}
+
+fn distance2(a: Point3<f32>, b: Point3<f32>) -> f32 {
+    let (x, y, z) = (a.x - b.x, a.y - b.y, a.z - b.z);
+    x * x + y * y + z * z
+}
 
 #[derive(Debug)]
 pub struct ChunkBuffer {
     center: ChunkPos,
     view_dist: u8,
-    buffer: HashMap<ChunkPos, VertexBuffer<FaceVertex>>
+    // number of chunks meshed by the last update
+    meshed: usize,
+    buffer: HashMap<ChunkPos, ChunkMesh>,
+    // meshes to rebuild because a neighbour changed
+    stale: HashSet<ChunkPos>,
 }
 
 impl ChunkBuffer {
@@ -19,27 +115,96 @@ impl ChunkBuffer {
         ChunkBuffer {
             buffer: HashMap::new(),
             view_dist: view_dist,
+            meshed: 0,
             center: Point::origin(),
+            stale: HashSet::new(),
         }
     }
 
-    pub fn update(&mut self, display: &Display, game: &GameState, center: ChunkPos) {
+    // eye is the position of the camera inside of the center chunk
+    pub fn update(&mut self, display: &Display, game: &GameState, center: ChunkPos, eye: Point3<f32>) {
         let mut new_buf = HashMap::new();
+        self.meshed = 0;
         let surroundings = Chunks::around(self.view_dist, center);
         for pos in surroundings {
-            let vb = self.buffer.remove(&pos);
-            if vb.is_none() || game.chunk(pos).is_dirty() {
-                new_buf.insert(pos, VertexBuffer::new(display, &game.chunk(pos).as_faces()).unwrap());
-            } else if let Some(vb) = vb {
-                new_buf.insert(pos, vb);
+            if !game.is_loaded(pos) {
+                continue;
+            }
+            let rel_eye = Point3::new(
+                eye.x + ((center.x - pos.x) * 16) as f32,
+                eye.y + ((center.y - pos.y) * 16) as f32,
+                eye.z + ((center.z - pos.z) * 16) as f32,
+            );
+            let chunk = game.chunk(pos);
+            let mesh = self.buffer.remove(&pos);
+            let mesh_version = mesh.as_ref().map(|mesh| mesh.version);
+            if outdated(mesh_version, chunk.version(), self.stale.contains(&pos)) {
+                new_buf.insert(pos, ChunkMesh::new(display, &chunk, rel_eye));
+                self.meshed += 1;
+            } else if let Some(mut mesh) = mesh {
+                mesh.resort(display, rel_eye);
+                new_buf.insert(pos, mesh);
             }
         }
         self.buffer = new_buf;
         self.center = center;
+        // chunks outside of the view get meshed from scratch anyway
+        self.stale.clear();
+    }
+
+    // a block on the border of its chunk touches the neighbour as well,
+    // the chunk itself notices the change through its version
+    pub fn invalidate_block(&mut self, pos: Point3<i32>) {
+        let (chunk, block) = entity::split(pos);
+        self.stale.extend(border_neighbours(chunk, block));
+    }
+
+    // a new chunk borders all six neighbours
+    pub fn invalidate_around(&mut self, pos: ChunkPos) {
+        for axis in 0..3 {
+            let mut d = Vector3::new(0, 0, 0);
+            d[axis] = 1;
+            self.stale.insert(pos + d);
+            self.stale.insert(pos + -d);
+        }
+    }
+
+    // frees the gpu buffers of chunks that got unloaded
+    pub fn drop_chunks(&mut self, unloaded: &[ChunkPos]) {
+        for pos in unloaded {
+            self.buffer.remove(pos);
+        }
+    }
+
+    // every loaded chunk gets meshed again by the next update
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.stale.clear();
     }
 
     pub fn iter<'a>(&'a self) -> Vec<(ChunkPos, &'a VertexBuffer<FaceVertex>)> {
-        self.buffer.iter().map(|(pos, vb)| (Point3::from_vec(*pos - self.center), vb)).collect()
+        self.buffer.iter().map(|(pos, mesh)| (Point3::from_vec(*pos - self.center), &mesh.solid)).collect()
+    }
+
+    // translucent faces of all chunks, the farthest chunk comes first
+    pub fn iter_translucent<'a>(&'a self) -> Vec<(ChunkPos, &'a VertexBuffer<FaceVertex>)> {
+        let mut res: Vec<_> = self.buffer.iter()
+            .filter(|&(_, mesh)| !mesh.translucent.is_empty())
+            .map(|(pos, mesh)| (Point3::from_vec(*pos - self.center), &mesh.translucent_buffer))
+            .collect();
+        res.sort_by(|&(a, _), &(b, _)| {
+            let dist = |p: ChunkPos| p.x * p.x + p.y * p.y + p.z * p.z;
+            dist(b).cmp(&dist(a))
+        });
+        res
+    }
+
+    pub fn get_meshed(&self) -> usize {
+        self.meshed
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.buffer.values().fold(0, |acc, mesh| acc + mesh.solid.len() + mesh.translucent.len())
     }
 
     pub fn get_view_dist(&self) -> u8 {
@@ -49,4 +214,62 @@ impl ChunkBuffer {
     pub fn set_view_dist(&mut self, view_dist: u8) {
         self.view_dist = ::std::cmp::max(2, view_dist);
     }
+
+    // start and end of the distance fog in blocks,
+    // the fog is thick at the last chunk that is always loaded in every direction
+    pub fn get_fog_range(&self) -> (f32, f32) {
+        let end = (self.view_dist - 1) as f32 * 16.0;
+        (end * 0.6, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use super::*;
+
+    fn sorted(mut chunks: Vec<ChunkPos>) -> Vec<ChunkPos> {
+        chunks.sort_by(|a, b| (a.x, a.y, a.z).cmp(&(b.x, b.y, b.z)));
+        chunks
+    }
+
+    #[test]
+    fn blocks_inside_touch_no_neighbour() {
+        let chunk = Point3::new(2, -1, 5);
+        assert!(border_neighbours(chunk, Point3::new(1, 1, 1)).is_empty());
+        assert!(border_neighbours(chunk, Point3::new(14, 7, 14)).is_empty());
+    }
+
+    #[test]
+    fn blocks_on_a_face_touch_one_neighbour() {
+        let chunk = Point3::new(2, -1, 5);
+        assert_eq!(border_neighbours(chunk, Point3::new(0, 7, 7)), vec![Point3::new(1, -1, 5)]);
+        assert_eq!(border_neighbours(chunk, Point3::new(7, 15, 7)), vec![Point3::new(2, 0, 5)]);
+        assert_eq!(border_neighbours(chunk, Point3::new(7, 7, 0)), vec![Point3::new(2, -1, 4)]);
+    }
+
+    #[test]
+    fn blocks_on_an_edge_touch_two_neighbours() {
+        let chunk = Point3::new(0, 0, 0);
+        assert_eq!(sorted(border_neighbours(chunk, Point3::new(15, 0, 8))),
+                   vec![Point3::new(0, -1, 0), Point3::new(1, 0, 0)]);
+    }
+
+    #[test]
+    fn blocks_in_a_corner_touch_three_neighbours() {
+        let chunk = Point3::new(0, 0, 0);
+        assert_eq!(sorted(border_neighbours(chunk, Point3::new(0, 0, 0))),
+                   vec![Point3::new(-1, 0, 0), Point3::new(0, -1, 0), Point3::new(0, 0, -1)]);
+        assert_eq!(sorted(border_neighbours(chunk, Point3::new(15, 15, 15))),
+                   vec![Point3::new(0, 0, 1), Point3::new(0, 1, 0), Point3::new(1, 0, 0)]);
+    }
+
+    #[test]
+    fn meshes_are_built_when_missing_changed_or_stale() {
+        assert!(outdated(None, 3, false));
+        assert!(outdated(Some(2), 3, false));
+        assert!(outdated(Some(3), 3, true));
+        assert!(!outdated(Some(3), 3, false));
+    }
 }
@@ -0,0 +1,120 @@
+use glium::Surface;
+
+use logic::command::Commands;
+use logic::game::GameState;
+use super::text::{ Text, LINE_HEIGHT };
+
+// older lines get dropped
+const MAX_HISTORY: usize = 100;
+// lines shown above the input
+const VISIBLE_LINES: usize = 10;
+// baseline of the input line, just above the hud
+const INPUT_POS: (f32, f32) = (-1.0, -0.85);
+
+// chat and commands typed into the game, commands start with a slash
+pub struct Console {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            input: String::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // starts a new input with the given text
+    pub fn open(&mut self, input: &str) {
+        self.open = true;
+        self.input = input.to_string();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.input.clear();
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        if !c.is_control() {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.history.push(line);
+        if self.history.len() > MAX_HISTORY {
+            let n = self.history.len() - MAX_HISTORY;
+            self.history.drain(..n);
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // completes the last word as far as all candidates agree and lists them if there are several
+    pub fn complete(&mut self, commands: &Commands, game: &GameState) {
+        let candidates = commands.complete(game, &self.input);
+        match candidates.len() {
+            0 => {},
+            1 => self.input = format!("{} ", candidates[0]),
+            _ => {
+                let common = candidates.iter().skip(1).fold(candidates[0].clone(), |common, candidate| {
+                    common.chars().zip(candidate.chars())
+                        .take_while(|&(a, b)| a == b)
+                        .map(|(a, _)| a)
+                        .collect()
+                });
+                if common.len() > self.input.len() {
+                    self.input = common;
+                }
+                let words: Vec<&str> = candidates.iter()
+                    .filter_map(|candidate| candidate.split(' ').last())
+                    .collect();
+                self.push(words.join(" "));
+            },
+        }
+    }
+
+    // runs the input as a command or posts it as a chat message and closes the console
+    pub fn submit(&mut self, commands: &Commands, game: &mut GameState) {
+        let line = self.input.trim().to_string();
+        self.close();
+        if line.is_empty() {
+            return;
+        }
+        if line.starts_with('/') {
+            self.push(format!("> {}", line));
+            match commands.execute(game, &line) {
+                Ok(message) => self.push(message),
+                Err(e)      => self.push(format!("error: {}", e)),
+            }
+        } else {
+            self.push(format!("<player> {}", line));
+        }
+    }
+
+    // the input at the bottom with the latest history above it
+    pub fn draw<S: Surface>(&self, surface: &mut S, text: &Text) {
+        if !self.open {
+            return;
+        }
+        text.draw_at(surface, &format!("> {}_", self.input), INPUT_POS, (1.0, 1.0, 1.0, 1.0));
+        for (i, line) in self.history.iter().rev().take(VISIBLE_LINES).enumerate() {
+            let pos = (INPUT_POS.0, INPUT_POS.1 + (i + 1) as f32 * LINE_HEIGHT);
+            text.draw_at(surface, line, pos, (0.8, 0.8, 0.8, 1.0));
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use cgmath::Deg;
+
+// number of frames the frame time statistics are taken over
+const FRAME_SAMPLES: usize = 120;
+
+#[derive(Clone, Debug)]
+pub struct FrameTimes {
+    // frame times in milliseconds, newest last
+    times: VecDeque<f32>,
+}
+
+impl FrameTimes {
+    pub fn new() -> FrameTimes {
+        FrameTimes {
+            times: VecDeque::with_capacity(FRAME_SAMPLES),
+        }
+    }
+
+    pub fn push(&mut self, frame: Duration) {
+        if self.times.len() == FRAME_SAMPLES {
+            self.times.pop_front();
+        }
+        self.times.push_back(frame.as_secs() as f32 * 1000.0 + frame.subsec_nanos() as f32 / 1_000_000.0);
+    }
+
+    pub fn fps(&self) -> f32 {
+        let total: f32 = self.times.iter().fold(0.0, |acc, &t| acc + t);
+        if total > 0.0 {
+            self.times.len() as f32 * 1000.0 / total
+        } else {
+            0.0
+        }
+    }
+
+    // frame time in milliseconds that p percent of the frames stay below
+    pub fn percentile(&self, p: f32) -> f32 {
+        if self.times.is_empty() {
+            return 0.0;
+        }
+        let mut sorted: Vec<f32> = self.times.iter().cloned().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let idx = ((sorted.len() - 1) as f32 * p / 100.0).round() as usize;
+        sorted[idx]
+    }
+}
+
+// phi is normalized to 0..360, 0 when looking north and growing clockwise
+pub fn compass(phi: Deg<f32>) -> &'static str {
+    const DIRECTIONS: [&'static str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    DIRECTIONS[((phi.s + 22.5) / 45.0) as usize % 8]
+}
@@ -0,0 +1,43 @@
+use cgmath::{ Point3, Vector3 };
+
+use logic::block::{ self, Block, BlockState };
+use logic::chunks::ChunkPos;
+use logic::entity::{ Entities, Kind };
+use types::{ Aabb, Face };
+use super::FaceVertex;
+
+// every entity as a textured box, corners relative to the origin chunk
+// so they can be drawn like a chunk at offset zero
+pub fn as_faces(entities: &Entities, origin: ChunkPos) -> Vec<FaceVertex> {
+    let mut faces = Vec::new();
+    for (_, entity) in entities.iter() {
+        let size = entity.size;
+        let bbox = entity.bbox();
+        let offset = Vector3::new(
+            bbox.min.x - (origin.x * 16) as f32,
+            bbox.min.y - (origin.y * 16) as f32,
+            bbox.min.z - (origin.z * 16) as f32,
+        );
+        // texture coordinates come from the box at the origin, so every box shows the same part
+        let local = Aabb::new(Point3::new(0.0, 0.0, 0.0), Point3::new(size.x, size.y, size.z));
+        faces.extend(block::box_faces(&local).into_iter().map(|(f, c, uv)|
+            FaceVertex {
+                corner: (c + offset).into(),
+                uv: uv,
+                face: f as u8,
+                tex_id: texture(entity.kind, f),
+                tex_rot: 0,
+                pos: [0, 0, 0],
+            }
+        ));
+    }
+    faces
+}
+
+fn texture(kind: Kind, face: Face) -> u8 {
+    match kind {
+        Kind::Mob { .. }          => Block::Sand.texture(face, BlockState::new()),
+        Kind::Item { item, .. }   => item.texture(face),
+        Kind::Projectile { .. }   => Block::Stone.texture(face, BlockState::new()),
+    }
+}
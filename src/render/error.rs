@@ -8,12 +8,15 @@ use glium::texture::TextureCreationError;
 use glium::framebuffer::RenderBufferCreationError;
 use glium::framebuffer::ValidationError;
 
+use logic::world::WorldError;
+
 #[derive(Debug)]
 pub enum RendererCreationError<T> {
     ContextCreationError(GliumCreationError<T>),
     ProgramCreationError(ProgramCreationError),
-    TextCreationError(TextCreationError),
     PickerCreationError(PickerCreationError),
+    TextureCreationError(TextureCreationError),
+    WorldLoadError(WorldError),
 }
 
 impl<T: Error> fmt::Display for RendererCreationError<T> {
@@ -24,10 +27,12 @@ impl<T: Error> fmt::Display for RendererCreationError<T> {
                 write!(fmt, "{}: {}", self.description(), s),
             ProgramCreationError(ref s) =>
                 write!(fmt, "{}: {}", self.description(), s),
-            TextCreationError(ref s) =>
-                write!(fmt, "{}: {}", self.description(), s),
             PickerCreationError(ref s) =>
                 write!(fmt, "{}: {}", self.description(), s),
+            TextureCreationError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
+            WorldLoadError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
         }
     }
 }
@@ -40,10 +45,12 @@ impl<T: Error> Error for RendererCreationError<T> {
                 "Error while creating the Render Context",
             ProgramCreationError(_) =>
                 "Error while compiling the Shader",
-            TextCreationError(_) =>
-                "Error while creating the Fontrenderer",
             PickerCreationError(_) =>
                 "Error while creating the Picker",
+            TextureCreationError(_) =>
+                "Error while loading the block textures",
+            WorldLoadError(_) =>
+                "Error while opening the world",
         }
     }
 
@@ -53,8 +60,9 @@ impl<T: Error> Error for RendererCreationError<T> {
         match *self {
             ContextCreationError(ref s) => Some(s),
             ProgramCreationError(ref s) => Some(s),
-            TextCreationError(ref s) => Some(s),
             PickerCreationError(ref s) => Some(s),
+            TextureCreationError(ref s) => Some(s),
+            WorldLoadError(ref s) => Some(s),
         }
     }
 }
@@ -71,18 +79,24 @@ impl<T: Error> From<ProgramCreationError> for RendererCreationError<T> {
     }
 }
 
-impl<T: Error> From<TextCreationError> for RendererCreationError<T> {
-    fn from(err: TextCreationError) -> Self {
-        RendererCreationError::TextCreationError(err)
-    }
-}
-
 impl<T: Error> From<PickerCreationError> for RendererCreationError<T> {
     fn from(err: PickerCreationError) -> Self {
         RendererCreationError::PickerCreationError(err)
     }
 }
 
+impl<T: Error> From<TextureCreationError> for RendererCreationError<T> {
+    fn from(err: TextureCreationError) -> Self {
+        RendererCreationError::TextureCreationError(err)
+    }
+}
+
+impl<T: Error> From<WorldError> for RendererCreationError<T> {
+    fn from(err: WorldError) -> Self {
+        RendererCreationError::WorldLoadError(err)
+    }
+}
+
 #[derive (Debug)]
 pub enum PickerCreationError {
     TextureCreationError(TextureCreationError),
@@ -208,3 +222,78 @@ impl From<()> for TextCreationError {
         TextCreationError::BuildFontTextureError
     }
 }
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    WriteFileError(IOError),
+    TextureCreationError(TextureCreationError),
+    RenderBufferCreationError(RenderBufferCreationError),
+    ValidationError(ValidationError),
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        use self::ScreenshotError::*;
+        match *self {
+            WriteFileError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
+            TextureCreationError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
+            RenderBufferCreationError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
+            ValidationError(ref s) =>
+                write!(fmt, "{}: {}", self.description(), s),
+        }
+    }
+}
+
+impl Error for ScreenshotError {
+    fn description(&self) -> &str {
+        use self::ScreenshotError::*;
+        match *self {
+            WriteFileError(_) =>
+                "Could not write screenshot",
+            TextureCreationError(_) =>
+                "Could not create offscreen texture",
+            RenderBufferCreationError(_) =>
+                "Could not create offscreen depth buffer",
+            ValidationError(_) =>
+                "Could not validate offscreen framebuffer",
+        }
+    }
+
+    #[inline]
+    fn cause(&self) -> Option<&Error> {
+        use self::ScreenshotError::*;
+        match *self {
+            WriteFileError(ref s) => Some(s),
+            TextureCreationError(ref s) => Some(s),
+            RenderBufferCreationError(ref s) => Some(s),
+            ValidationError(ref s) => Some(s),
+        }
+    }
+}
+
+impl From<IOError> for ScreenshotError {
+    fn from(err: IOError) -> Self {
+        ScreenshotError::WriteFileError(err)
+    }
+}
+
+impl From<TextureCreationError> for ScreenshotError {
+    fn from(err: TextureCreationError) -> Self {
+        ScreenshotError::TextureCreationError(err)
+    }
+}
+
+impl From<RenderBufferCreationError> for ScreenshotError {
+    fn from(err: RenderBufferCreationError) -> Self {
+        ScreenshotError::RenderBufferCreationError(err)
+    }
+}
+
+impl From<ValidationError> for ScreenshotError {
+    fn from(err: ValidationError) -> Self {
+        ScreenshotError::ValidationError(err)
+    }
+}
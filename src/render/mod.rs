@@ -5,15 +5,23 @@ pub mod shader;
 pub mod picking;
 pub mod text;
 pub mod texture;
+pub mod screenshot;
+pub mod debug;
+pub mod entities;
 pub mod chunk_buffer;
+pub mod console;
 
 #[derive(Clone, Copy, Debug)]
 pub struct FaceVertex {
     pub face: u8,
+    pub tex_id: u8,
+    // quarter turns of the texture
+    pub tex_rot: u8,
     pub pos: [u8; 3],
     pub corner: [f32; 3],
+    pub uv: [f32; 2],
 }
-implement_vertex!(FaceVertex, face, pos, corner);
+implement_vertex!(FaceVertex, face, tex_id, tex_rot, pos, corner, uv);
 
 #[derive(Clone, Copy, Debug)]
 pub struct WireVertex {
@@ -1,6 +1,6 @@
-use std;
+use std::collections::HashMap;
 
-use glium::{ self, Display, Surface, DrawParameters };
+use glium::{ self, Display, Surface, DrawParameters, SyncFence };
 use glium::framebuffer::{ DepthRenderBuffer, SimpleFrameBuffer };
 use glium::index::IndicesSource;
 use glium::program::Program;
@@ -8,15 +8,48 @@ use glium::texture::{ UnsignedTexture2d, UncompressedUintFormat, MipmapsOption,
 use glium::texture::pixel_buffer::PixelBuffer;
 use glium::uniforms::Uniforms;
 use glium::vertex::MultiVerticesSource;
-use cgmath::Point3;
+use cgmath::{ Point, Point3 };
 
 use super::error::PickerCreationError;
 use types::Face;
 use logic::chunks::ChunkPos;
 use logic::chunk::BlockPos;
 
+// a picking id consists of two channels:
+// first:  1 bit valid | 3 bits face | 3 * 4 bits block position
+// second: 3 * 10 bits chunk offset relative to the camera chunk, biased by CHUNK_BIAS
+// this has to match the picking shader
+const CHUNK_BITS: u32 = 10;
+const CHUNK_MASK: u32 = (1 << CHUNK_BITS) - 1;
+const CHUNK_BIAS: i32 = 1 << (CHUNK_BITS - 1);
+
+pub type PickId = (u32, u32);
+
+// number of readbacks in flight, a readback issued in frame N gets consumed in frame N + 2
+const RING_SIZE: usize = 3;
+// largest region around the crosshair that can be read back
+const MAX_RADIUS: u32 = 4;
+
+// one readback into a pixel buffer
+struct Readback {
+    pbo: PixelBuffer<PickId>,
+    // signaled once the copy into the pbo is done, None if nothing is in flight
+    fence: Option<SyncFence>,
+    // the camera chunk the picked chunk offsets are relative to
+    center: ChunkPos,
+    // index of the crosshair pixel inside of the pbo
+    mid: usize,
+    len: usize,
+}
+
 pub struct Picker {
-    pbo: PixelBuffer<u32>,
+    ring: Vec<Readback>,
+    // next readback to issue, the oldest one gets consumed in the same frame
+    next: usize,
+    // side length of the read region is 2 * radius + 1
+    radius: u32,
+    // camera chunk of the current picking frame
+    center: ChunkPos,
     tex: UnsignedTexture2d,
     depth: DepthRenderBuffer,
     program: Program,
@@ -27,7 +60,7 @@ impl Picker {
         use super::shader;
         let tex = try!(UnsignedTexture2d::empty_with_format(
             display,
-            UncompressedUintFormat::U32,
+            UncompressedUintFormat::U32U32,
             MipmapsOption::NoMipmap,
             1024, 768,
         ));
@@ -36,8 +69,19 @@ impl Picker {
             DepthFormat::F32,
             1024, 768,
         ));
+        let side = (2 * MAX_RADIUS + 1) as usize;
+        let ring = (0..RING_SIZE).map(|_| Readback {
+            pbo: PixelBuffer::new_empty(display, side * side),
+            fence: None,
+            center: Point::origin(),
+            mid: 0,
+            len: 0,
+        }).collect();
         Ok(Picker {
-            pbo: PixelBuffer::new_empty(display, 1),
+            ring: ring,
+            next: 0,
+            radius: 0,
+            center: Point::origin(),
             tex: tex,
             depth: depth,
             program: try!(Program::from_source(
@@ -49,44 +93,110 @@ impl Picker {
         })
     }
 
-    pub fn pick(&self) -> Option<(ChunkPos, BlockPos, Face)> {
+    // 0 only reads the pixel under the crosshair, clamped to MAX_RADIUS
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = ::std::cmp::min(radius, MAX_RADIUS);
+    }
+
+    // starts reading back the last picking frame and returns the result of the readback
+    // from two frames ago, the returned chunk is absolute
+    pub fn pick(&mut self, display: &Display) -> Option<(ChunkPos, BlockPos, Face)> {
+        self.issue(display);
+        self.next = (self.next + 1) % RING_SIZE;
+        // the slot after the one just issued is the oldest one
+        self.consume(self.next)
+    }
+
+    fn issue(&mut self, display: &Display) {
         let (width, height) = self.get_dimensions();
+        let (mid_x, mid_y) = (width / 2, height / 2);
+        let left = mid_x.saturating_sub(self.radius);
+        let bottom = mid_y.saturating_sub(self.radius);
         let read_target = glium::Rect {
-            left: std::cmp::max(width/2, 1) - 1,
-            bottom: height - (std::cmp::max(height/2, 1) - 1),
-            width: 1,
-            height: 1,
+            left: left,
+            bottom: bottom,
+            width: ::std::cmp::min(mid_x + self.radius + 1, width) - left,
+            height: ::std::cmp::min(mid_y + self.radius + 1, height) - bottom,
         };
 
-        //read pixel
-        if read_target.left < width
-        && read_target.bottom < height {
-            self.tex
-                .main_level()
-                .first_layer()
-                .into_image(None).unwrap()
-                .raw_read_to_pixel_buffer(&read_target, &self.pbo);
-            return self.pbo.read().map(|x| x[0]).ok().and_then(|x| if x & 1 != 0 { Some(x) } else { None }).map(Picker::decode);
-        } else {
+        let readback = &mut self.ring[self.next];
+        self.tex
+            .main_level()
+            .first_layer()
+            .into_image(None).unwrap()
+            .raw_read_to_pixel_buffer(&read_target, &readback.pbo);
+        // without fences the read below just blocks a little longer
+        readback.fence = SyncFence::new(display).ok();
+        readback.center = self.center;
+        readback.mid = ((mid_y - bottom) * read_target.width + (mid_x - left)) as usize;
+        readback.len = (read_target.width * read_target.height) as usize;
+    }
+
+    fn consume(&mut self, slot: usize) -> Option<(ChunkPos, BlockPos, Face)> {
+        let readback = &mut self.ring[slot];
+        if readback.len == 0 {
             return None;
         }
+        if let Some(fence) = readback.fence.take() {
+            // two frames old, so this is done already
+            fence.wait();
+        }
+        let ids = match readback.pbo.read() {
+            Ok(ids) => ids,
+            Err(_) => return None,
+        };
+        Picker::filter(&ids[..readback.len], readback.mid).and_then(Picker::decode).map(|(c, b, f)| {
+            (readback.center + c.to_vec(), b, f)
+        })
     }
 
-    fn decode(val: u32) -> (ChunkPos, BlockPos, Face) {
-        assert!(val & 1 != 0);
-        (
+    // the id covering most of the region wins, ties go to the crosshair pixel
+    fn filter(ids: &[PickId], mid: usize) -> Option<PickId> {
+        let mut votes = HashMap::new();
+        for &id in ids.iter().filter(|&&(block, _)| block & 1 != 0) {
+            *votes.entry(id).or_insert(0) += 1;
+        }
+        let mid_votes = votes.get(&ids[mid]).cloned().unwrap_or(0);
+        votes.into_iter()
+            .max_by_key(|&(_, n)| n)
+            .map(|(id, n)| if n == mid_votes { ids[mid] } else { id })
+    }
+
+    // the chunk is relative to the camera chunk and has to fit into CHUNK_BITS per axis,
+    // the shader does the same for every face it draws
+    pub fn encode(chunk: ChunkPos, block: BlockPos, face: Face) -> PickId {
+        let block =
+              (block.x as u32) << 12
+            | (block.y as u32) << 8
+            | (block.z as u32) << 4
+            | (face as u32)    << 1
+            | 1;
+        let chunk =
+              ((chunk.x + CHUNK_BIAS) as u32 & CHUNK_MASK) << (2 * CHUNK_BITS)
+            | ((chunk.y + CHUNK_BIAS) as u32 & CHUNK_MASK) << CHUNK_BITS
+            | ((chunk.z + CHUNK_BIAS) as u32 & CHUNK_MASK);
+        (block, chunk)
+    }
+
+    // None for pixels without any face
+    pub fn decode(id: PickId) -> Option<(ChunkPos, BlockPos, Face)> {
+        let (block, chunk) = id;
+        if block & 1 == 0 {
+            return None;
+        }
+        Some((
             Point3::new(
-                ((val >> 20) & 0x3) as i32 - 1,
-                ((val >> 18) & 0x3) as i32 - 1,
-                ((val >> 16) & 0x3) as i32 - 1,
+                ((chunk >> (2 * CHUNK_BITS)) & CHUNK_MASK) as i32 - CHUNK_BIAS,
+                ((chunk >> CHUNK_BITS) & CHUNK_MASK) as i32 - CHUNK_BIAS,
+                (chunk & CHUNK_MASK) as i32 - CHUNK_BIAS,
             ),
             Point3::new(
-                ((val >> 12) & 0xF) as u8,
-                ((val >>  8) & 0xF) as u8,
-                ((val >>  4) & 0xF) as u8,
+                ((block >> 12) & 0xF) as u8,
+                ((block >>  8) & 0xF) as u8,
+                ((block >>  4) & 0xF) as u8,
             ),
-            Face::from((val >> 1) & 0x7),
-        )
+            Face::from((block >> 1) & 0x7),
+        ))
     }
 
     pub fn resize(&mut self, display: &Display, dimensions: (u32, u32)) {
@@ -94,7 +204,7 @@ impl Picker {
             let (width, height) = dimensions;
             self.tex = UnsignedTexture2d::empty_with_format(
                 display,
-                UncompressedUintFormat::U32,
+                UncompressedUintFormat::U32U32,
                 MipmapsOption::NoMipmap,
                 width, height,
             ).unwrap();
@@ -106,7 +216,10 @@ impl Picker {
         }
     }
 
-    pub fn clear(&mut self, display: &Display) {
+    // center is the camera chunk all drawn chunk offsets are relative to
+    pub fn clear(&mut self, display: &Display, center: ChunkPos) {
+        self.center = center;
+
         let mut target = SimpleFrameBuffer::with_depth_buffer(display, &self.tex, &self.depth).unwrap();
 
         //clearing the attachments
@@ -149,3 +262,61 @@ impl Picker {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use types::Face;
+    use super::*;
+
+    #[test]
+    fn every_face_and_block_survives_the_round_trip() {
+        let chunks = [
+            Point3::new(0, 0, 0),
+            Point3::new(-512, -512, -512),
+            Point3::new(511, 511, 511),
+            Point3::new(-512, 0, 511),
+            Point3::new(511, -1, -512),
+        ];
+        for &chunk in chunks.iter() {
+            for face in Face::values() {
+                for x in 0..16 {
+                    for y in 0..16 {
+                        for z in 0..16 {
+                            let block = Point3::new(x, y, z);
+                            assert_eq!(Picker::decode(Picker::encode(chunk, block, face)), Some((chunk, block, face)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_bias_covers_exactly_the_chunk_bits() {
+        let block = Point3::new(0, 0, 0);
+        let (_, lowest) = Picker::encode(Point3::new(-512, -512, -512), block, Face::Top);
+        let (_, highest) = Picker::encode(Point3::new(511, 511, 511), block, Face::Top);
+        assert_eq!(lowest, 0);
+        assert_eq!(highest, (1 << 30) - 1);
+    }
+
+    #[test]
+    fn ids_match_the_shader() {
+        // the formula of shader::picking::GEOMETRY written out
+        let (chunk, block, face) = (Point3::new(-3, 7, 200), Point3::new(15, 2, 9), Face::South);
+        let expected = (
+            15 << 12 | 2 << 8 | 9 << 4 | (face as u32) << 1 | 1,
+            (512 - 3) << 20 | (512 + 7) << 10 | (512 + 200),
+        );
+        assert_eq!(Picker::encode(chunk, block, face), expected);
+    }
+
+    #[test]
+    fn cleared_pixels_pick_nothing() {
+        assert_eq!(Picker::decode((0, 0)), None);
+        let (block, chunk) = Picker::encode(Point3::new(1, 2, 3), Point3::new(4, 5, 6), Face::West);
+        assert_eq!(Picker::decode((block & !1, chunk)), None);
+    }
+}
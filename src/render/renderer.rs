@@ -1,41 +1,70 @@
 
 use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::time::{ Duration, Instant };
 
-use image;
-use cgmath::{ Point, Point3, Matrix4 };
+use cgmath::{ self, Point, Point3, Matrix4 };
 use glium::{ self, glutin, DisplayBuild, Surface, Display, VertexBuffer };
 use glium::program::Program;
 use glium::backend::glutin_backend::WinRef;
 use glium::draw_parameters::DrawParameters;
+use glium::framebuffer::{ DepthRenderBuffer, SimpleFrameBuffer };
 use glium::index::{ NoIndices, PrimitiveType };
+use glium::texture::{ DepthFormat, RawImage2d, SrgbTexture2dArray, Texture2d };
 
 use super::camera::Camera;
+use super::console::Console;
 use super::text::Text;
-use super::error::RendererCreationError;
+use super::error::{ RendererCreationError, ScreenshotError };
 use super::picking::Picker;
-use super::{ shader, WireVertex };
+use super::{ entities, shader, texture, WireVertex };
 use super::chunk_buffer::ChunkBuffer;
+use super::debug::{ self, FrameTimes };
+use super::screenshot::{ self, Screenshot };
+use ::config::Config;
 use ::event::Event;
+use ::logic::{ entity, player };
+use ::logic::player::GameMode;
+use ::logic::command::Commands;
+use ::logic::events::GameEvent;
 use ::logic::game::GameState;
+use ::logic::plugin::SpawnProtection;
 
 const MOUSE_SENSIVITY: f32 = 0.1;
+const SKY_COLOR: [f32; 3] = [0.0, 0.0, 1.0];
+// fog range while the eye is inside of a block
+const IN_BLOCK_FOG: (f32, f32) = (0.0, 4.0);
+// after a long hang the simulation skips ahead instead of catching up
+const MAX_TICKS_PER_FRAME: u32 = 10;
+// the baseline of the health and hunger line, bottom left
+const HUD_POS: (f32, f32) = (-1.0, -0.95);
 
 pub struct Renderer {
     display: Display,
     picker: Picker,
     cube_program: Program,
     wire_program: Program,
+    crack_program: Program,
+    block_textures: SrgbTexture2dArray,
     camera: Camera,
     fov: f32, //in radians
-    text: Text,
+    // None if no font could be loaded, the text overlay is disabled then
+    text: Option<Text>,
     stats: bool,
+    frame_times: FrameTimes,
+    // simulated time not yet covered by a tick, in seconds
+    tick_lag: f32,
     fill: bool,
+    screenshot: Option<Screenshot>,
+    console: Console,
+    commands: Commands,
     chunk_buffer: ChunkBuffer,
     game: GameState,
 }
 
 impl Renderer {
-    pub fn new() -> Result<Renderer, RendererCreationError<glutin::CreationError>> {
+    // the problems that didn't stop the renderer come back with it, like Config::load does
+    pub fn new(config: &Config) -> Result<(Renderer, Vec<String>), RendererCreationError<glutin::CreationError>> {
         let display = try!(glutin::WindowBuilder::new()
             .with_depth_buffer(24)
             .with_vsync()
@@ -45,9 +74,28 @@ impl Renderer {
             window.set_cursor(glium::glutin::MouseCursor::Crosshair);
         }
 
-        Ok(Renderer {
-            picker: try!(Picker::new(&display)),
-            text: try!(Text::new(&display, "/usr/share/fonts/TTF/NotoSans-Regular.ttf", 24)),
+        let mut problems = Vec::new();
+        let text = match Text::load(&display, config.font_path.as_ref().map(|p| &p[..]), config.font_size, &mut problems) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                problems.push(format!("Text overlay disabled: {}", e));
+                None
+            },
+        };
+
+        let mut game = try!(GameState::new(config));
+        if config.spawn_protection > 0 {
+            game.register_plugin(Box::new(SpawnProtection::new(config.spawn_protection)));
+        }
+        game.start();
+
+        let renderer = Renderer {
+            picker: {
+                let mut picker = try!(Picker::new(&display));
+                picker.set_radius(config.pick_radius);
+                picker
+            },
+            text: text,
             cube_program: try!(Program::from_source(
                 &display,
                 shader::cube::VERTEX,
@@ -60,107 +108,374 @@ impl Renderer {
                 shader::wire::FRAGMENT,
                 Some(shader::wire::GEOMETRY),
             )),
+            crack_program: try!(Program::from_source(
+                &display,
+                shader::wire::VERTEX,
+                shader::wire::FRAGMENT,
+                Some(shader::crack::GEOMETRY),
+            )),
+            block_textures: try!(texture::load_block_textures(&display)),
             camera: Camera::at(Point3::new(20.0, 20.0, 20.0), Point::origin()),
             fov: PI / 3.0,
             stats: false,
+            frame_times: FrameTimes::new(),
+            tick_lag: 0.0,
             fill: true,
-            game: GameState::new(),
+            screenshot: None,
+            console: Console::new(),
+            commands: Commands::defaults(),
+            game: game,
             chunk_buffer: ChunkBuffer::new(2),
             display: display,
-        })
+        };
+        Ok((renderer, problems))
     }
 
     pub fn game_loop(mut self) {
-        //TODO move to own module
-        let image = image::load(::std::io::Cursor::new(&include_bytes!(
-            "../../assets/textures/dirt.png"
-        )[..]),image::PNG).unwrap().to_rgba();
-        let image_dimensions = image.dimensions();
-        let image = glium::texture::RawImage2d::from_raw_rgba_reversed(image.into_raw(), image_dimensions);
-        let texture = glium::texture::SrgbTexture2d::new(&self.display, image).unwrap();
-        let texture_sampler = glium::uniforms::Sampler::new(&texture)
-            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
-            .minify_filter(glium::uniforms::MinifySamplerFilter::Nearest);
-
-        let wires_buffer: VertexBuffer<WireVertex> = VertexBuffer::immutable(&self.display, &[
-            WireVertex { corner: [1.0, 1.0, 1.0] },
-            WireVertex { corner: [0.0, 0.0, 0.0] },
-        ]).unwrap();
-
+        self.follow_player();
+        let mut last_frame = Instant::now();
         loop {
-            self.chunk_buffer.update(&self.display, &self.game, self.camera.get_chunk_pos());
-
-            {//pick from previous frame
-                let pick_res = self.picker.pick().map(|(c, b, f)| {
-                    (
-                        self.camera.get_chunk_pos() + c.to_vec(),
-                        b,
-                        f
-                    )
-                });
-                self.game.set_selected_block(pick_res);
-            }
+            self.update_chunks();
+            self.chunk_buffer.update(&self.display, &self.game, self.camera.get_chunk_pos(), self.camera.get_pos());
+
+            //pick from two frames ago, without waiting for the gpu
+            let pick_res = self.picker.pick(&self.display);
+            self.game.set_selected_block(pick_res);
 
             // draw
             let mut target = self.display.draw();
-            self.picker.resize(&self.display, target.get_dimensions());
+            let dimensions = target.get_dimensions();
+            self.picker.resize(&self.display, dimensions);
+            self.picker.clear(&self.display, self.camera.get_chunk_pos());
+            self.draw_picking(dimensions);
+            self.draw_world(&mut target, dimensions);
 
-            target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
-            self.picker.clear(&self.display);
+            if let (true, Some(text)) = (self.stats, self.text.as_ref()) {
+                text.draw_lines(&mut target, &self.debug_lines(), (1.0, 1.0, 0.0, 1.0));
+            }
+            if let Some(text) = self.text.as_ref() {
+                text.draw_at(&mut target, &self.hud_line(), HUD_POS, (1.0, 1.0, 1.0, 1.0));
+                self.console.draw(&mut target, text);
+            }
 
-            let perspective = self.get_perspective(target.get_dimensions());
-            let view = self.camera.view_matrix();
+            target.finish().unwrap();
 
-            let vp: [[f32; 4]; 4]  = (perspective * view).into();
+            let now = Instant::now();
+            let frame_time = now.duration_since(last_frame);
+            self.frame_times.push(frame_time);
+            self.game.set_player_input(self.camera.get_movement());
+            self.game.set_player_look(self.camera.get_phi().s, self.camera.get_theta().s);
+            self.run_ticks(frame_time);
+            self.handle_game_events();
+            self.report_errors();
+            self.camera.set_world_pos(self.game.player().eye());
+            last_frame = now;
 
-            let params = self.get_params();
+            if let Some(kind) = self.screenshot.take() {
+                match self.take_screenshot(kind) {
+                    Ok(path) => println!("Saved screenshot to {}", path.display()),
+                    Err(e)   => println!("{}", e),
+                }
+            }
 
-            for (pos, vb) in self.chunk_buffer.iter() {
-                let pos = [pos[0], pos[1], pos[2]];
-                self.picker.draw(
-                    &self.display,
-                    vb,
-                    &NoIndices(PrimitiveType::LinesList),
-                    &uniform! { vp: vp, chunk: pos },
-                    &params
-                );
+            if !self.handle_events() {
+                self.game.save();
+                self.report_errors();
+                return;
+            }
+            self.camera.update();
+        }
+    }
+
+    // renders a single frame offscreen and saves it, useful for automated checks,
+    // it shows what the loaded player sees
+    pub fn capture(mut self, scale: u32) -> Result<PathBuf, ScreenshotError> {
+        self.follow_player();
+        self.update_chunks();
+        self.chunk_buffer.update(&self.display, &self.game, self.camera.get_chunk_pos(), self.camera.get_pos());
+        self.take_screenshot(Screenshot::Offscreen(scale))
+    }
+
+    // the camera sits in the eyes of the player from now on, looking where it did when saved
+    fn follow_player(&mut self) {
+        self.camera.set_flying(false);
+        self.camera.set_world_pos(self.game.player().eye());
+        let (phi, theta) = (self.game.player().phi, self.game.player().theta);
+        self.camera.set_look(cgmath::deg(phi), cgmath::deg(theta));
+    }
+
+        // runs the game at a fixed tick rate, independent of the frame rate
+    fn run_ticks(&mut self, frame_time: Duration) {
+        self.tick_lag += frame_time.as_secs() as f32 + frame_time.subsec_nanos() as f32 / 1_000_000_000.0;
+        let mut ticks = 0;
+        while self.tick_lag >= entity::TICK {
+            if ticks == MAX_TICKS_PER_FRAME {
+                self.tick_lag = 0.0;
+                break;
+            }
+            self.game.tick();
+            self.tick_lag -= entity::TICK;
+            ticks += 1;
+        }
+    }
+
+    // prints what went wrong in the game and shows it in the console
+    fn report_errors(&mut self) {
+        for error in self.game.take_errors() {
+            println!("{}", error);
+            self.console.push(error);
+        }
+    }
+
+    // loads the chunks around the camera
+    fn update_chunks(&mut self) {
+        let view_dist = self.game.get_view_dist();
+        self.chunk_buffer.set_view_dist(view_dist);
+        self.game.update_chunks(&[self.camera.get_chunk_pos()], view_dist);
+    }
+
+    // reacts to what changed in the world during the last ticks
+    fn handle_game_events(&mut self) {
+        for event in self.game.take_events() {
+            match event {
+                GameEvent::BlockChanged { pos, .. } => self.chunk_buffer.invalidate_block(pos),
+                GameEvent::ChunkLoaded(pos)         => self.chunk_buffer.invalidate_around(pos),
+                GameEvent::ChunkUnloaded(pos)       => self.chunk_buffer.drop_chunks(&[pos]),
+                GameEvent::Resync                   => self.chunk_buffer.clear(),
+                _ => {},
+            }
+        }
+    }
+
+    fn take_screenshot(&self, kind: Screenshot) -> Result<PathBuf, ScreenshotError> {
+        let image = match kind {
+            Screenshot::Window => self.display.read_front_buffer(),
+            Screenshot::Offscreen(scale) => {
+                let (width, height) = self.display.get_framebuffer_dimensions();
+                try!(self.render_offscreen((width * scale, height * scale)))
+            },
+        };
+        Ok(try!(screenshot::save(image)))
+    }
+
+    fn render_offscreen(&self, dimensions: (u32, u32)) -> Result<RawImage2d<'static, u8>, ScreenshotError> {
+        let (width, height) = dimensions;
+        let color = try!(Texture2d::empty(&self.display, width, height));
+        let depth = try!(DepthRenderBuffer::new(&self.display, DepthFormat::I24, width, height));
+        {
+            let mut target = try!(SimpleFrameBuffer::with_depth_buffer(&self.display, &color, &depth));
+            self.draw_world(&mut target, dimensions);
+        }
+        Ok(color.read())
+    }
+
+    fn draw_picking(&mut self, dimensions: (u32, u32)) {
+        let vp = self.get_vp(dimensions);
+        let params = self.get_params();
+
+        for (pos, vb) in self.chunk_buffer.iter().into_iter().chain(self.chunk_buffer.iter_translucent()) {
+            let pos = [pos[0], pos[1], pos[2]];
+            self.picker.draw(
+                &self.display,
+                vb,
+                &NoIndices(PrimitiveType::LinesList),
+                &uniform! { vp: vp, chunk: pos },
+                &params
+            );
+        }
+    }
+
+    fn draw_world<S: Surface>(&self, target: &mut S, dimensions: (u32, u32)) {
+        let (fog_color, (fog_start, fog_end)) = self.get_fog();
+        target.clear_color_and_depth((fog_color[0], fog_color[1], fog_color[2], 1.0), 1.0);
+
+        let vp = self.get_vp(dimensions);
+        let eye: [f32; 3] = self.camera.get_pos().into();
+
+        let texture_sampler = glium::uniforms::Sampler::new(&self.block_textures)
+            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+            .minify_filter(glium::uniforms::MinifySamplerFilter::Nearest);
+
+        let params = self.get_params();
+
+        for (pos, vb) in self.chunk_buffer.iter() {
+            let pos = [pos[0], pos[1], pos[2]];
+            target.draw(
+                vb,
+                &NoIndices(PrimitiveType::LinesList),
+                &self.cube_program,
+                &uniform! {
+                    vp : vp,
+                    chunk: pos,
+                    eye: eye,
+                    tex: texture_sampler,
+                    alpha_cutoff: 0.5f32,
+                    fog_color: fog_color,
+                    fog_start: fog_start,
+                    fog_end: fog_end,
+                },
+                &params
+            ).unwrap();
+        }
+
+        let entity_faces = entities::as_faces(self.game.entities(), self.camera.get_chunk_pos());
+        if !entity_faces.is_empty() {
+            let entity_buffer = VertexBuffer::new(&self.display, &entity_faces).unwrap();
+            target.draw(
+                &entity_buffer,
+                &NoIndices(PrimitiveType::LinesList),
+                &self.cube_program,
+                &uniform! {
+                    vp : vp,
+                    chunk: [0, 0, 0i32],
+                    eye: eye,
+                    tex: texture_sampler,
+                    alpha_cutoff: 0.5f32,
+                    fog_color: fog_color,
+                    fog_start: fog_start,
+                    fog_end: fog_end,
+                },
+                &params
+            ).unwrap();
+        }
+
+        // translucent faces come last, back to front and without writing depth
+        let translucent_params = self.get_translucent_params();
+        for (pos, vb) in self.chunk_buffer.iter_translucent() {
+            let pos = [pos[0], pos[1], pos[2]];
+            target.draw(
+                vb,
+                &NoIndices(PrimitiveType::LinesList),
+                &self.cube_program,
+                &uniform! {
+                    vp : vp,
+                    chunk: pos,
+                    eye: eye,
+                    tex: texture_sampler,
+                    alpha_cutoff: 0.0f32,
+                    fog_color: fog_color,
+                    fog_start: fog_start,
+                    fog_end: fog_end,
+                },
+                &translucent_params
+            ).unwrap();
+        }
 
+        if let Some((chunk, pos, face)) = self.game.get_selected_block() {
+            // one outline per box of the block, the geometry shader expands the opposite corners
+            let corners: Vec<_> = self.game.block(chunk, pos).shape()
+                .boxes(self.game.state(chunk, pos))
+                .iter()
+                .flat_map(|b| vec![WireVertex { corner: b.max.into() }, WireVertex { corner: b.min.into() }])
+                .collect();
+            let wire_buffer = VertexBuffer::new(&self.display, &corners).unwrap();
+            let stage = match self.game.get_mining() {
+                Some((c, p, progress)) if c == chunk && p == pos => Some((progress * 10.0) as u32 + 1),
+                _ => None,
+            };
+            let pos: [u32; 3] = pos.to_vec().cast().into();
+            let chunk: [i32; 3] = (chunk - self.camera.get_chunk_pos()).into();
+            target.draw(
+                &wire_buffer,
+                &NoIndices(PrimitiveType::LinesList),
+                &self.wire_program,
+                &uniform! { vp: vp, pos: pos, chunk: chunk, color: [0.0, 0.0, 0.0, 1.0f32] },
+                &params
+            ).unwrap();
+
+            // one more crack for every tenth of the way
+            if let Some(stage) = stage {
                 target.draw(
-                    vb,
+                    &wire_buffer,
                     &NoIndices(PrimitiveType::LinesList),
-                    &self.cube_program,
+                    &self.crack_program,
                     &uniform! {
-                        vp : vp,
-                        chunk: pos,
-                        tex: texture_sampler,
+                        vp: vp,
+                        pos: pos,
+                        chunk: chunk,
+                        face: face as u32,
+                        stage: stage,
+                        color: [0.1, 0.1, 0.1, 1.0f32],
                     },
                     &params
                 ).unwrap();
             }
-            if let Some((chunk, pos, _)) = self.game.get_selected_block() {
-                let pos: [u32; 3] = pos.to_vec().cast().into();
-                let chunk: [i32; 3] = (chunk - self.camera.get_chunk_pos()).into();
-                target.draw(
-                    &wires_buffer,
-                    &NoIndices(PrimitiveType::LinesList),
-                    &self.wire_program,
-                    &uniform! { vp: vp, pos: pos, chunk: chunk, color: [0.0, 0.0, 0.0, 1.0f32] },
-                    &params
-                ).unwrap();
-            }
-
-            if self.stats {
-                self.text.draw(&mut target, &format!("{}", self.camera), (1.0, 1.0, 0.0, 1.0));
-            }
+        }
+    }
 
-            target.finish().unwrap();
+    // the stats only matter in survival
+    fn hud_line(&self) -> String {
+        let player = self.game.player();
+        if player.mode != GameMode::Survival {
+            return format!("{}", player.mode);
+        }
+        let mut line = format!(
+            "health {}/{}  hunger {}/{}",
+            player.health.ceil(), player::MAX_HEALTH,
+            player.hunger.ceil(), player::MAX_HUNGER,
+        );
+        if player.air < player::MAX_AIR {
+            line.push_str(&format!("  air {}/{}", player.air, player::MAX_AIR));
+        }
+        line
+    }
 
+    fn debug_lines(&self) -> Vec<String> {
+        let chunk_stats = self.game.chunk_stats();
+        let inventory: Vec<String> = self.game.player().inventory.slots().iter()
+            .filter_map(|slot| *slot)
+            .map(|stack| format!("{} x{}", stack.item, stack.count))
+            .collect();
+        let inventory = inventory.join(", ");
+        let block = self.camera.get_block_pos();
+        let target = match self.game.get_selected_block() {
+            Some((chunk, pos, face)) => format!(
+                "target: {}, {}, {} {:?} {:?}",
+                chunk.x * 16 + pos.x as i32,
+                chunk.y * 16 + pos.y as i32,
+                chunk.z * 16 + pos.z as i32,
+                face,
+                self.game.block(chunk, pos),
+            ),
+            None => "target: none".to_string(),
+        };
+        vec![
+            format!(
+                "{:.0} fps, frame time p50: {:.1}ms p95: {:.1}ms p99: {:.1}ms",
+                self.frame_times.fps(),
+                self.frame_times.percentile(50.0),
+                self.frame_times.percentile(95.0),
+                self.frame_times.percentile(99.0),
+            ),
+            format!("{}", self.camera),
+            format!("facing: {}", debug::compass(self.camera.get_phi())),
+            format!("biome: {:?}", self.game.biome(self.camera.get_chunk_pos(), block.x, block.z)),
+            target,
+            format!(
+                "chunks: {} loaded, {} meshed, view distance {}",
+                self.game.loaded_chunks(),
+                self.chunk_buffer.get_meshed(),
+                self.chunk_buffer.get_view_dist(),
+            ),
+            format!("vertices: {}", self.chunk_buffer.vertex_count()),
+            format!("entities: {}, tick {}", self.game.entities().len(), self.game.get_ticks()),
+            format!("inventory: {}", inventory),
+            format!("chunk memory: {:.1} MiB", self.game.chunk_memory() as f32 / (1024.0 * 1024.0)),
+            format!(
+                "chunk io: {} loaded, {} generated, {} saved, {} unloaded, {} evicted",
+                chunk_stats.loaded,
+                chunk_stats.generated,
+                chunk_stats.saved,
+                chunk_stats.unloaded,
+                chunk_stats.evicted,
+            ),
+        ]
+    }
 
-            if !self.handle_events() {
-                return;
-            }
-            self.camera.update();
-        }
+    fn get_vp(&self, dimensions: (u32, u32)) -> [[f32; 4]; 4] {
+        let perspective = self.get_perspective(dimensions);
+        let view = self.camera.view_matrix();
+        (perspective * view).into()
     }
 
     fn get_perspective(&self, dimensions: (u32, u32)) -> Matrix4<f32> {
@@ -180,6 +495,19 @@ impl Renderer {
         )
     }
 
+    // the fog matches the sky, unless the eye is inside of a block
+    fn get_fog(&self) -> ([f32; 3], (f32, f32)) {
+        let (chunk, pos) = (self.camera.get_chunk_pos(), self.camera.get_block_pos());
+        let block = self.game.block(chunk, pos);
+        // the eye has to be inside of the actual shape, not only the block
+        let eye = Point3::from_vec(self.camera.get_pos().to_vec() - pos.to_vec().cast());
+        let inside = block.shape().boxes(self.game.state(chunk, pos)).iter().any(|b| b.contains(eye));
+        match block.fog_color() {
+            Some(color) if inside => (color, IN_BLOCK_FOG),
+            _                     => (SKY_COLOR, self.chunk_buffer.get_fog_range()),
+        }
+    }
+
     fn get_params<'b, 'c>(&'b self) -> DrawParameters<'c> {
         use glium::draw_parameters::PolygonMode::{ Fill, Line };
         use glium::draw_parameters::DepthTest;
@@ -196,6 +524,17 @@ impl Renderer {
         }
     }
 
+    fn get_translucent_params<'b, 'c>(&'b self) -> DrawParameters<'c> {
+        DrawParameters {
+            depth: glium::Depth {
+                write: false,
+                .. self.get_params().depth
+            },
+            blend: glium::Blend::alpha_blending(),
+            .. self.get_params()
+        }
+    }
+
     fn handle_events(&mut self) -> bool {
         for ev in self.display.poll_events() {
             use glium::glutin::Event as E;
@@ -211,16 +550,36 @@ impl Renderer {
                     // screen coordinates decrease to the top, unlike theta
                     self.camera.add_theta((mid_y - mouse_y as i32) as f32 * MOUSE_SENSIVITY);
                 },
+                // while the console is open it gets all the keys
+                E::KeyboardInput(Pressed, _, Some(Return)) if self.console.is_open() =>
+                    self.console.submit(&self.commands, &mut self.game),
+                E::KeyboardInput(Pressed, _, Some(Back)) if self.console.is_open() => self.console.backspace(),
+                E::KeyboardInput(Pressed, _, Some(Tab)) if self.console.is_open() => self.console.complete(&self.commands, &self.game),
+                E::KeyboardInput(Pressed, _, Some(Escape)) if self.console.is_open() => self.console.close(),
+                E::ReceivedCharacter(c) if self.console.is_open() => self.console.type_char(c),
+                E::KeyboardInput(..) | E::MouseInput(..) if self.console.is_open() => {},
+                E::ReceivedCharacter(c) if c == 't' || c == '/' => {
+                    self.console.open(if c == '/' { "/" } else { "" });
+                    self.camera.stop();
+                    self.game.set_attacking(false);
+                },
                 E::KeyboardInput(Pressed, _, Some(Add)) => {
-                    let n = self.chunk_buffer.get_view_dist();
-                    self.chunk_buffer.set_view_dist(n + 1);
+                    let n = self.game.get_view_dist();
+                    self.game.set_view_dist(n.saturating_add(1));
                 },
                 E::KeyboardInput(Pressed, _, Some(Subtract)) => {
-                    let n = self.chunk_buffer.get_view_dist();
-                    self.chunk_buffer.set_view_dist(n - 1);
+                    let n = self.game.get_view_dist();
+                    self.game.set_view_dist(n.saturating_sub(1));
                 },
                 E::KeyboardInput(Pressed, _, Some(F1)) => self.fill = !self.fill,
+                E::KeyboardInput(Pressed, _, Some(F2)) => self.screenshot = Some(Screenshot::Window),
                 E::KeyboardInput(Pressed, _, Some(F3)) => self.stats = ! self.stats,
+                E::KeyboardInput(Pressed, _, Some(F4)) => {
+                    let mode = self.game.get_game_mode().next();
+                    self.game.set_game_mode(mode);
+                },
+                E::KeyboardInput(Pressed, _, Some(F12)) =>
+                    self.screenshot = Some(Screenshot::Offscreen(screenshot::HIGH_RES_SCALE)),
                 E::KeyboardInput(Pressed, _, Some(Escape)) => return false,
                 _ => {
                     use ::event::Event::*;
@@ -228,8 +587,12 @@ impl Renderer {
                         Move { dir: d, toogle: t } => self.camera.mov (d, t),
                         Turn { dir: d, toogle: t } => self.camera.turn(d, t),
                         Fly  { dir: d, toogle: t } => self.camera.fly (d, t),
-                        Attack                     => self.game.attack(),
-                        UseItem                    => self.game.place(),
+                        Attack { toogle: t }       => self.game.set_attacking(t),
+                        UseItem                    => self.game.place(self.camera.get_facing()),
+                        Throw                      => {
+                            self.game.throw(self.camera.get_world_pos(), self.camera.get_forward());
+                        },
+                        Select { slot: s }         => self.game.select(s),
                         _ => {}
                     }
                 },
@@ -0,0 +1,70 @@
+use std::fs;
+use std::io::Error as IOError;
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use image;
+use glium::texture::RawImage2d;
+
+const DIRECTORY: &'static str = "screenshots";
+
+// how many times larger than the window a high resolution screenshot is
+pub const HIGH_RES_SCALE: u32 = 4;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Screenshot {
+    // read back what the window shows right now
+    Window,
+    // render the frame again into an offscreen framebuffer, scaled by the given factor
+    Offscreen(u32),
+}
+
+// writes the rgba image into the screenshots directory, returns the path of the new file
+pub fn save(image: RawImage2d<u8>) -> Result<PathBuf, IOError> {
+    try!(fs::create_dir_all(DIRECTORY));
+    let path = unused_path(Path::new(DIRECTORY), &timestamp(SystemTime::now()));
+
+    // opengl hands out the bottom row first
+    let row = image.width as usize * 4;
+    let mut pixels = Vec::with_capacity(image.data.len());
+    for line in image.data.chunks(row).rev() {
+        pixels.extend_from_slice(line);
+    }
+
+    try!(image::save_buffer(&path, &pixels, image.width, image.height, image::RGBA(8)));
+    Ok(path)
+}
+
+// more than one screenshot a second gets a counter appended
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.png", name));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.png", name, n));
+        n += 1;
+    }
+    path
+}
+
+// UTC time as YYYY-MM-DD_HH-MM-SS
+fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+
+    // civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year, month, day,
+        rem / 3600, rem / 60 % 60, rem % 60,
+    )
+}
@@ -0,0 +1,60 @@
+    // same inputs as the wire program, the cracks are drawn as lines on one face of every box
+    pub const GEOMETRY: &'static str = r#"
+        #version 150
+
+        layout(lines) in;
+        layout(line_strip, max_vertices = 20) out;
+
+        uniform mat4 vp;
+        uniform ivec3 chunk;
+        uniform uvec3 pos;
+        // see types::Face
+        uniform uint face;
+        // number of crack segments to draw, 0..10
+        uniform uint stage;
+
+        // segments in face coordinates, later ones branch off earlier ones
+        const vec2 CRACK[20] = vec2[20](
+            vec2(0.5, 0.5),   vec2(0.3, 0.35),
+            vec2(0.5, 0.5),   vec2(0.7, 0.6),
+            vec2(0.3, 0.35),  vec2(0.15, 0.4),
+            vec2(0.7, 0.6),   vec2(0.85, 0.55),
+            vec2(0.5, 0.5),   vec2(0.45, 0.75),
+            vec2(0.3, 0.35),  vec2(0.35, 0.12),
+            vec2(0.45, 0.75), vec2(0.55, 0.9),
+            vec2(0.7, 0.6),   vec2(0.75, 0.8),
+            vec2(0.5, 0.5),   vec2(0.65, 0.3),
+            vec2(0.65, 0.3),  vec2(0.8, 0.15)
+        );
+
+        // lifts the cracks off the face, so they don't fight with it for depth
+        const float LIFT = 0.002;
+
+        vec3 lo;
+        vec3 hi;
+
+        vec3 on_face(vec2 t) {
+            float x = mix(lo.x, hi.x, t.x);
+            float y = mix(lo.y, hi.y, t.y);
+            float zu = mix(lo.z, hi.z, t.x);
+            float zv = mix(lo.z, hi.z, t.y);
+            if (face == 0u) return vec3(x, hi.y + LIFT, zv);
+            if (face == 1u) return vec3(x, lo.y - LIFT, zv);
+            if (face == 2u) return vec3(x, y, lo.z - LIFT);
+            if (face == 3u) return vec3(hi.x + LIFT, y, zu);
+            if (face == 4u) return vec3(x, y, hi.z + LIFT);
+            return vec3(lo.x - LIFT, y, zu);
+        }
+
+        void main() {
+            lo = min(gl_in[0].gl_Position.xyz, gl_in[1].gl_Position.xyz);
+            hi = max(gl_in[0].gl_Position.xyz, gl_in[1].gl_Position.xyz);
+            vec3 offset = vec3(ivec3(pos) + chunk * 16);
+
+            for (uint i = 0u; i < stage && i < 10u; i++) {
+                gl_Position = vp * vec4(on_face(CRACK[2u * i]) + offset, 1.0); EmitVertex();
+                gl_Position = vp * vec4(on_face(CRACK[2u * i + 1u]) + offset, 1.0); EmitVertex();
+                EndPrimitive();
+            }
+        }
+    "#;
@@ -2,10 +2,20 @@
         #version 150
 
         in uint face;
+        in uint tex_id;
+        in uint tex_rot;
         in uvec3 pos;
         in vec3 corner;
+        in vec2 uv;
+
+        flat out uint v_tex_id;
+        flat out uint v_tex_rot;
+        out vec2 v_uv;
 
         void main() {
+            v_uv = uv;
+            v_tex_id = tex_id;
+            v_tex_rot = tex_rot;
             gl_Position = vec4(corner + pos, 1.0);
         }
     "#;
@@ -15,10 +25,25 @@
         layout(lines) in;
         layout(triangle_strip, max_vertices = 4) out;
 
+        flat in uint v_tex_id[2];
+        flat in uint v_tex_rot[2];
+        in vec2 v_uv[2];
+
         out vec2 g_texcoord;
+        out float g_dist;
+        flat out uint g_tex_id;
 
         uniform ivec3 chunk;
         uniform mat4 vp;
+        uniform vec3 eye;
+
+        // turns the texture coordinates by quarter turns
+        vec2 rotate(vec2 t, uint turns) {
+            for (uint i = 0u; i < turns; i++) {
+                t = vec2(t.y, 1.0 - t.x);
+            }
+            return t;
+        }
 
         void main() {
             // Two input vertices will be the first and last vertex of the quad
@@ -37,11 +62,22 @@
                 c.y = d.y;
             }
 
+            a += ivec4(chunk, 0) * 16;
+            b += ivec4(chunk, 0) * 16;
+            c += ivec4(chunk, 0) * 16;
+            d += ivec4(chunk, 0) * 16;
+
+            g_tex_id = v_tex_id[0];
+
             // Emit the vertices of the quad
-            g_texcoord = vec2(0.0, 1.0); gl_Position = vp * (a + ivec4(chunk, 0) * 16); EmitVertex();
-            g_texcoord = vec2(1.0, 1.0); gl_Position = vp * (b + ivec4(chunk, 0) * 16); EmitVertex();
-            g_texcoord = vec2(0.0, 0.0); gl_Position = vp * (c + ivec4(chunk, 0) * 16); EmitVertex();
-            g_texcoord = vec2(1.0, 0.0); gl_Position = vp * (d + ivec4(chunk, 0) * 16); EmitVertex();
+            // b and c take one texture coordinate from each end, like their position
+            uint r = v_tex_rot[0];
+            vec2 ta = v_uv[0];
+            vec2 td = v_uv[1];
+            g_texcoord = rotate(ta, r);               g_dist = distance(a.xyz, eye); gl_Position = vp * a; EmitVertex();
+            g_texcoord = rotate(vec2(td.x, ta.y), r); g_dist = distance(b.xyz, eye); gl_Position = vp * b; EmitVertex();
+            g_texcoord = rotate(vec2(ta.x, td.y), r); g_dist = distance(c.xyz, eye); gl_Position = vp * c; EmitVertex();
+            g_texcoord = rotate(td, r);               g_dist = distance(d.xyz, eye); gl_Position = vp * d; EmitVertex();
             EndPrimitive();
         }
     "#;
@@ -49,11 +85,24 @@
         #version 150
 
         in vec2 g_texcoord;
+        in float g_dist;
+        flat in uint g_tex_id;
         out vec4 color;
 
-        uniform sampler2D tex;
+        uniform sampler2DArray tex;
+        // pixels with less alpha get discarded, used for cutout blocks
+        uniform float alpha_cutoff;
+        uniform vec3 fog_color;
+        uniform float fog_start;
+        uniform float fog_end;
 
         void main() {
-            color = texture(tex, g_texcoord);
+            // 1.0 -> no fog, 0.0 -> only fog
+            float visibility = clamp((fog_end - g_dist) / (fog_end - fog_start), 0.0, 1.0);
+            vec4 tex_color = texture(tex, vec3(g_texcoord, g_tex_id));
+            if (tex_color.a < alpha_cutoff) {
+                discard;
+            }
+            color = vec4(mix(fog_color, tex_color.rgb, visibility), tex_color.a);
         }
     "#;
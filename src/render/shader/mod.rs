@@ -1,3 +1,4 @@
+pub mod crack;
 pub mod cube;
 pub mod picking;
 pub mod wire;
@@ -23,32 +23,35 @@
         flat in uint v_face[2];
         flat in uvec3 v_pos[2];
 
-        flat out uint g_id;
+        flat out uvec2 g_id;
 
         uniform ivec3 chunk;
         uniform mat4 vp;
 
         void main() {
+            // chunk offsets have 10 bits per axis, biased by 512
             if (
-                   chunk.x >= -1 && chunk.x <= 1
-                && chunk.y >= -1 && chunk.y <= 1
-                && chunk.z >= -1 && chunk.z <= 1
+                   chunk.x >= -512 && chunk.x <= 511
+                && chunk.y >= -512 && chunk.y <= 511
+                && chunk.z >= -512 && chunk.z <= 511
             ){
-                //calculate face id
+                //calculate face id, see Picker::encode
+                // first channel:
                 // first bit means empty -> 1 bit
                 // 6 faces               -> 3 bits
                 // 0..15 for pos         -> 4 bits * 3
-                // -1..1(0..2) for chunk -> 2 bits * 3
-                // total: 22 bits
-                uint id =
-                      uint(chunk.x) + 1u << 20u
-                    | uint(chunk.y) + 1u << 18u
-                    | uint(chunk.z) + 1u << 16u
-                    | uint(v_pos[0].x)   << 12u
-                    | uint(v_pos[0].y)   << 8u
-                    | uint(v_pos[0].z)   << 4u
-                    | uint(v_face[0])    << 1u
-                    | 1u;
+                // second channel:
+                // -512..511 for chunk   -> 10 bits * 3
+                uvec2 id = uvec2(
+                      uint(v_pos[0].x) << 12u
+                    | uint(v_pos[0].y) << 8u
+                    | uint(v_pos[0].z) << 4u
+                    | uint(v_face[0])  << 1u
+                    | 1u,
+                      uint(chunk.x + 512) << 20u
+                    | uint(chunk.y + 512) << 10u
+                    | uint(chunk.z + 512)
+                );
 
                 // Two input vertices will be the first and last vertex of the quad
                 vec4 a = gl_in[0].gl_Position;
@@ -78,8 +81,8 @@
     pub const FRAGMENT: &'static str = r#"
         #version 150
 
-        flat in uint g_id;
-        out uint f_id;
+        flat in uvec2 g_id;
+        out uvec2 f_id;
 
         void main() {
             f_id = g_id;
@@ -88,10 +91,10 @@
     pub const FRAGMENT_ALT: &'static str = r#"
             #version 150
 
-            flat in uint g_id;
+            flat in uvec2 g_id;
             out vec4 color;
 
             void main() {
-                color = vec4(g_id, g_id, g_id, 1.0);
+                color = vec4(g_id.x, g_id.y, 0.0, 1.0);
             }
     "#;
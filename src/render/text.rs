@@ -1,4 +1,5 @@
 use std::fs::File;
+use std::io::{ Cursor, Read };
 use std::path::Path;
 
 use glium_text::{ self, TextSystem, FontTexture, TextDisplay };
@@ -6,23 +7,45 @@ use glium::{ Display, Surface };
 
 use super::error::TextCreationError;
 
+// used whenever no font is configured or the configured one can't be loaded
+const EMBEDDED_FONT: &'static [u8] = include_bytes!("../../assets/fonts/DejaVuSansMono.ttf");
 
 pub struct Text {
     system: TextSystem,
     font: FontTexture,
 }
 
-const MATRIX: [[f32; 4]; 4] = [
-    [0.025, 0.0,   0.0,   0.0],
-    [0.0,   0.025, 0.0,   0.0],
-    [0.0,   0.0,   0.025, 0.0],
-    [-1.0,  0.96,  0.0,   1.0],
-];
+// size of the glyphs in normalized device coordinates
+const SCALE: f32 = 0.025;
+// distance between the baselines of two lines
+pub const LINE_HEIGHT: f32 = SCALE * 1.6;
+// baseline of the first line in the top left corner
+const ORIGIN: (f32, f32) = (-1.0, 0.96);
 
 impl Text {
-    pub fn new(display: &Display, font_path: &str, font_size: u32) -> Result<Text, TextCreationError> {
+    // tries the font at font_path first and falls back to the embedded font,
+    // why the configured one wasn't used ends up in problems
+    pub fn load(display: &Display, font_path: Option<&str>, font_size: u32, problems: &mut Vec<String>) -> Result<Text, TextCreationError> {
+        if let Some(path) = font_path {
+            match Text::from_file(display, path, font_size) {
+                Ok(text) => return Ok(text),
+                Err(e) => problems.push(format!("Could not load font {}, using the embedded font: {}", path, e)),
+            }
+        }
+        Text::from_embedded(display, font_size)
+    }
+
+    pub fn from_file(display: &Display, font_path: &str, font_size: u32) -> Result<Text, TextCreationError> {
         let file = try!(File::open(&Path::new(font_path)));
-        let font = try!(FontTexture::new(display, file, font_size));
+        Text::new(display, file, font_size)
+    }
+
+    pub fn from_embedded(display: &Display, font_size: u32) -> Result<Text, TextCreationError> {
+        Text::new(display, Cursor::new(EMBEDDED_FONT), font_size)
+    }
+
+    fn new<R: Read>(display: &Display, font: R, font_size: u32) -> Result<Text, TextCreationError> {
+        let font = try!(FontTexture::new(display, font, font_size));
 
         Ok(Text {
             system: TextSystem::new(display),
@@ -32,7 +55,26 @@ impl Text {
 
     // Surface MUST belong to display of constructor
     pub fn draw<S: Surface>(&self, surface: &mut S, text: &str, color: (f32, f32, f32, f32)) {
+        self.draw_at(surface, text, ORIGIN, color);
+    }
+
+    // draws every line below the previous one, starting in the top left corner
+    pub fn draw_lines<S: Surface>(&self, surface: &mut S, lines: &[String], color: (f32, f32, f32, f32)) {
+        for (i, line) in lines.iter().enumerate() {
+            let pos = (ORIGIN.0, ORIGIN.1 - i as f32 * LINE_HEIGHT);
+            self.draw_at(surface, line, pos, color);
+        }
+    }
+
+    // pos is the start of the baseline in normalized device coordinates
+    pub fn draw_at<S: Surface>(&self, surface: &mut S, text: &str, pos: (f32, f32), color: (f32, f32, f32, f32)) {
+        let matrix = [
+            [SCALE, 0.0,   0.0,   0.0],
+            [0.0,   SCALE, 0.0,   0.0],
+            [0.0,   0.0,   SCALE, 0.0],
+            [pos.0, pos.1, 0.0,   1.0],
+        ];
         let text = TextDisplay::new(&self.system, &self.font, text);
-        glium_text::draw(&text, &self.system, surface, MATRIX, color);
+        glium_text::draw(&text, &self.system, surface, matrix, color);
     }
 }
@@ -1,10 +1,32 @@
-//pub fn loadTexture(path: &str) -> SrgbTexture2d {
-//    let image = image::load(std::io::Cursor::new(&include_bytes!(
-//        "../assets/textures/dirt.png"
-//    )[..]),image::PNG).unwrap().to_rgba();
-//    let image_dimensions = image.dimensions();
-//    let image = glium::texture::RawImage2d::from_raw_rgba_reversed(image.into_raw(), image_dimensions);
-//    let texture = glium::texture::SrgbTexture2d::new(&display, image).unwrap();
-//}
+use std::io::Cursor;
 
-//TODO somehow load a texture ...
+use image;
+use glium::Display;
+use glium::texture::{ RawImage2d, SrgbTexture2dArray, TextureCreationError };
+
+// layers of the block texture array, indexed by Block::texture
+const BLOCK_TEXTURES: [&'static [u8]; 13] = [
+    include_bytes!("../../assets/textures/dirt.png"),
+    include_bytes!("../../assets/textures/leaves.png"),
+    include_bytes!("../../assets/textures/glass.png"),
+    include_bytes!("../../assets/textures/water.png"),
+    include_bytes!("../../assets/textures/stone.png"),
+    include_bytes!("../../assets/textures/sand.png"),
+    include_bytes!("../../assets/textures/grass.png"),
+    include_bytes!("../../assets/textures/snow.png"),
+    include_bytes!("../../assets/textures/log.png"),
+    include_bytes!("../../assets/textures/log_top.png"),
+    include_bytes!("../../assets/textures/furnace_front.png"),
+    include_bytes!("../../assets/textures/furnace_side.png"),
+    include_bytes!("../../assets/textures/tall_grass.png"),
+];
+
+pub fn load_block_textures(display: &Display) -> Result<SrgbTexture2dArray, TextureCreationError> {
+    let images = BLOCK_TEXTURES.iter().map(|bytes| {
+        // the textures are baked into the binary, so they are known to be valid
+        let image = image::load(Cursor::new(*bytes), image::PNG).unwrap().to_rgba();
+        let image_dimensions = image.dimensions();
+        RawImage2d::from_raw_rgba_reversed(image.into_raw(), image_dimensions)
+    }).collect();
+    SrgbTexture2dArray::new(display, images)
+}
@@ -1,6 +1,6 @@
-use cgmath::Vector3;
+use cgmath::{ Point3, Vector3 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Face {
     Top,
     Bottom,
@@ -23,6 +23,27 @@ impl Face {
         ]
     }
 
+    pub fn opposite(self) -> Face {
+        use self::Face::*;
+        match self {
+            Top    => Bottom,
+            Bottom => Top,
+            North  => South,
+            East   => West,
+            South  => North,
+            West   => East,
+        }
+    }
+
+    pub fn axis(self) -> Axis {
+        use self::Face::*;
+        match self {
+            East  | West   => Axis::X,
+            Top   | Bottom => Axis::Y,
+            North | South  => Axis::Z,
+        }
+    }
+
     pub fn to_vec(self) -> Vector3<i8> {
         use self::Face::*;
         match self {
@@ -44,6 +65,79 @@ impl From<u32> for Face {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+// axis aligned box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Aabb {
+        Aabb {
+            min: min,
+            max: max,
+        }
+    }
+
+    pub fn contains(&self, p: Point3<f32>) -> bool {
+           p.x >= self.min.x && p.x < self.max.x
+        && p.y >= self.min.y && p.y < self.max.y
+        && p.z >= self.min.z && p.z < self.max.z
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] < other.max[i] && other.min[i] < self.max[i])
+    }
+
+    pub fn offset(&self, v: Vector3<f32>) -> Aabb {
+        Aabb::new(self.min + v, self.max + v)
+    }
+
+    // larger by d in every direction
+    pub fn grow(&self, d: f32) -> Aabb {
+        Aabb::new(
+            Point3::new(self.min.x - d, self.min.y - d, self.min.z - d),
+            Point3::new(self.max.x + d, self.max.y + d, self.max.z + d),
+        )
+    }
+
+    // the space covered while moving by v
+    pub fn expand(&self, v: Vector3<f32>) -> Aabb {
+        let mut res = *self;
+        for i in 0..3 {
+            if v[i] < 0.0 {
+                res.min[i] += v[i];
+            } else {
+                res.max[i] += v[i];
+            }
+        }
+        res
+    }
+
+    // how far this box can move by d along axis before it hits other
+    pub fn clip(&self, other: &Aabb, axis: usize, d: f32) -> f32 {
+        let overlaps = (0..3).filter(|&i| i != axis)
+            .all(|i| self.min[i] < other.max[i] && other.min[i] < self.max[i]);
+        if !overlaps {
+            d
+        } else if d > 0.0 && self.max[axis] <= other.min[axis] {
+            d.min(other.min[axis] - self.max[axis])
+        } else if d < 0.0 && self.min[axis] >= other.max[axis] {
+            d.max(other.max[axis] - self.min[axis])
+        } else {
+            d
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum HDirection {
     Forth,